@@ -1,7 +1,7 @@
 use log::{error, info, warn};
 
 use rosm_pbf_reader::dense::{new_dense_tag_reader, DenseNodeReader};
-use rosm_pbf_reader::{new_tag_reader, pbf, read_blob, Block, BlockParser, Error, RawBlock};
+use rosm_pbf_reader::{new_tag_reader, pbf, BlobReader, Block, BlockParser, Error, RawBlock};
 
 use std::cell::RefCell;
 use std::fs::File;
@@ -88,7 +88,7 @@ fn main() {
     if thread_count == 1 {
         let mut block_parser = BlockParser::default();
 
-        while let Some(result) = read_blob(&mut file) {
+        for result in BlobReader::new(&mut file) {
             match result {
                 Ok(raw_block) => parse_block(&mut block_parser, raw_block),
                 Err(error) => error!("Error during reading the next blob: {:?}", error),
@@ -100,7 +100,7 @@ fn main() {
         // Make the parser thread local to reduce memory allocation count
         thread_local!(static BLOCK_PARSER: RefCell<BlockParser> = RefCell::new(BlockParser::default()));
 
-        while let Some(result) = read_blob(&mut file) {
+        for result in BlobReader::new(&mut file) {
             match result {
                 Ok(raw_block) => {
                     thread_pool.execute(move || {
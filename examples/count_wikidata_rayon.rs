@@ -0,0 +1,54 @@
+use log::{error, info};
+
+use rosm_pbf_reader::dense::{new_dense_tag_reader, DenseNodeReader};
+use rosm_pbf_reader::parallel::par_map_reduce;
+use rosm_pbf_reader::{new_tag_reader, pbf, BlobReader, Error};
+
+use std::fs::File;
+
+fn count_wikidata(block: pbf::PrimitiveBlock) -> Result<usize, Error> {
+    let string_table = &block.stringtable;
+    let mut count = 0;
+
+    for group in &block.primitivegroup {
+        for way in &group.ways {
+            let tags = new_tag_reader(string_table, &way.keys, &way.vals);
+            count += tags.filter(|(key, _)| matches!(key, Ok("wikidata"))).count();
+        }
+
+        if let Some(dense_nodes) = &group.dense {
+            for node in DenseNodeReader::new(dense_nodes)? {
+                let tags = new_dense_tag_reader(string_table, node?.key_value_indices);
+                count += tags.filter(|(key, _)| matches!(key, Ok("wikidata"))).count();
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+fn main() {
+    let mut builder = env_logger::Builder::from_default_env();
+    builder.filter_level(log::LevelFilter::Info);
+    builder.init();
+
+    let pbf_path = std::env::args()
+        .nth(1)
+        .expect("Expected an OSM PBF file as first argument");
+    let file = File::open(pbf_path).unwrap();
+
+    let start = std::time::Instant::now();
+
+    let count = par_map_reduce(BlobReader::new(file), count_wikidata, || 0, |a, b| a + b);
+
+    match count {
+        Ok(count) => info!("Wikidata tag count: {count}"),
+        Err(error) => error!("Error during counting: {error:?}"),
+    }
+
+    info!(
+        "Finished in {:.2}s on {} thread(s)",
+        start.elapsed().as_secs_f64(),
+        rayon::current_num_threads()
+    );
+}
@@ -0,0 +1,626 @@
+//! Utilities for building primitive blocks from high-level elements.
+
+use crate::dense::DenseNodeWriter;
+use crate::element::{Member, Metadata};
+use crate::util::{new_string_table, LatLon};
+use crate::{pbf, Error};
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Options of a [`PrimitiveBlockBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuilderOptions {
+    /// Granularity of coordinates in nanodegrees. Values smaller than 1 are treated as 1.
+    pub granularity: i32,
+    /// Latitude offset of coordinates in nanodegrees.
+    pub lat_offset: i64,
+    /// Longitude offset of coordinates in nanodegrees.
+    pub lon_offset: i64,
+    /// Granularity of timestamps in milliseconds. Values smaller than 1 are treated as 1.
+    pub date_granularity: i32,
+    /// Maximum number of elements in a block. Values smaller than 1 are treated as 1.
+    pub max_element_count: usize,
+}
+
+impl Default for BuilderOptions {
+    /// Uses the default granularities and offsets of the OSM PBF format, with at most 8000 elements per block, as
+    /// recommended by the format.
+    fn default() -> Self {
+        Self {
+            granularity: 100,
+            lat_offset: 0,
+            lon_offset: 0,
+            date_granularity: 1000,
+            max_element_count: 8000,
+        }
+    }
+}
+
+/// Builder of [`pbf::PrimitiveBlock`]s from high-level nodes, ways and relations.
+///
+/// Strings are interned into the string table of the block, nodes are stored as dense nodes, and way refs and
+/// relation member IDs are delta encoded, so the built block can be read back with [`DenseNodeReader`],
+/// [`DeltaValueReader`] or the [`element`](crate::element) module.
+///
+/// [`DenseNodeReader`]: crate::dense::DenseNodeReader
+/// [`DeltaValueReader`]: crate::DeltaValueReader
+///
+/// # Examples
+///
+/// ```
+/// use rosm_pbf_reader::builder::PrimitiveBlockBuilder;
+/// use rosm_pbf_reader::util::LatLon;
+///
+/// let mut builder = PrimitiveBlockBuilder::new();
+///
+/// let location = LatLon::new(47_500_000_000, 19_040_000_000).unwrap();
+/// builder.add_node(1, location, [("amenity", "cafe")], None).unwrap();
+/// builder.add_way(2, [1, 3, 4, 1], [("building", "yes")], None).unwrap();
+///
+/// let block = builder.build();
+/// assert_eq!(block.primitivegroup.len(), 2);
+/// assert!(builder.is_empty());
+/// ```
+pub struct PrimitiveBlockBuilder {
+    options: BuilderOptions,
+    string_table: Vec<Vec<u8>>,
+    string_indices: HashMap<String, u32>,
+    dense_nodes: DenseNodeWriter,
+    ways: Vec<pbf::Way>,
+    relations: Vec<pbf::Relation>,
+}
+
+impl Default for PrimitiveBlockBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrimitiveBlockBuilder {
+    /// Creates a new, empty `PrimitiveBlockBuilder` with default options.
+    pub fn new() -> Self {
+        Self::with_options(BuilderOptions::default())
+    }
+
+    /// Creates a new, empty `PrimitiveBlockBuilder` with custom options.
+    pub fn with_options(options: BuilderOptions) -> Self {
+        let mut builder = Self {
+            options: BuilderOptions {
+                granularity: options.granularity.max(1),
+                date_granularity: options.date_granularity.max(1),
+                max_element_count: options.max_element_count.max(1),
+                ..options
+            },
+            string_table: Vec::new(),
+            string_indices: HashMap::new(),
+            dense_nodes: DenseNodeWriter::new(),
+            ways: Vec::new(),
+            relations: Vec::new(),
+        };
+
+        builder.reset_string_table();
+        builder
+    }
+
+    /// Returns the options of this builder.
+    pub fn options(&self) -> &BuilderOptions {
+        &self.options
+    }
+
+    /// Returns the number of elements added since the last build.
+    pub fn len(&self) -> usize {
+        self.dense_nodes.len() + self.ways.len() + self.relations.len()
+    }
+
+    /// Returns whether no elements have been added since the last build.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether the block is full, i.e. it has [`BuilderOptions::max_element_count`] elements.
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.options.max_element_count
+    }
+
+    /// Adds a node with its location, tags and optional metadata.
+    ///
+    /// The location is rounded to the granularity grid of the block.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the block is full, or the node can't be encoded with the options of the builder.
+    pub fn add_node<'t, Tags>(
+        &mut self,
+        id: i64,
+        location: LatLon,
+        tags: Tags,
+        metadata: Option<&Metadata<'_>>,
+    ) -> Result<(), Error>
+    where
+        Tags: IntoIterator<Item = (&'t str, &'t str)>,
+    {
+        self.check_full()?;
+
+        let lat = encode_coord(
+            location.lat(),
+            self.options.lat_offset,
+            self.options.granularity,
+            LatLon::MAX_LAT,
+        )?;
+        let lon = encode_coord(
+            location.lon(),
+            self.options.lon_offset,
+            self.options.granularity,
+            LatLon::MAX_LON,
+        )?;
+
+        // Dense nodes are delta encoded with their string indices, so strings have to be interned first
+        self.interning(|builder| {
+            let info = builder.encode_metadata(metadata)?;
+
+            let mut key_value_indices = Vec::new();
+            for (key, value) in tags {
+                key_value_indices.push(to_dense_index(builder.intern(key))?);
+                key_value_indices.push(to_dense_index(builder.intern(value))?);
+            }
+
+            builder
+                .dense_nodes
+                .push(id, lat, lon, &key_value_indices, info.as_ref())
+        })
+    }
+
+    /// Adds a way with the IDs of its nodes, tags and optional metadata.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the block is full, or the way can't be encoded with the options of the builder.
+    pub fn add_way<'t, Refs, Tags>(
+        &mut self,
+        id: i64,
+        refs: Refs,
+        tags: Tags,
+        metadata: Option<&Metadata<'_>>,
+    ) -> Result<(), Error>
+    where
+        Refs: IntoIterator<Item = i64>,
+        Tags: IntoIterator<Item = (&'t str, &'t str)>,
+    {
+        self.check_full()?;
+
+        let refs = delta_encode(refs)?;
+        let (info, keys, vals) = self.interning(|builder| {
+            let info = builder.encode_metadata(metadata)?;
+            let (keys, vals) = builder.intern_tags(tags);
+            Ok((info, keys, vals))
+        })?;
+
+        self.ways.push(pbf::Way {
+            id,
+            keys,
+            vals,
+            info,
+            refs,
+            ..Default::default()
+        });
+
+        Ok(())
+    }
+
+    /// Adds a relation with its members, tags and optional metadata.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the block is full, or the relation can't be encoded with the options of the builder.
+    pub fn add_relation<'t, 'm, Members, Tags>(
+        &mut self,
+        id: i64,
+        members: Members,
+        tags: Tags,
+        metadata: Option<&Metadata<'_>>,
+    ) -> Result<(), Error>
+    where
+        Members: IntoIterator<Item = Member<'m>>,
+        Tags: IntoIterator<Item = (&'t str, &'t str)>,
+    {
+        self.check_full()?;
+
+        let members: Vec<_> = members.into_iter().collect();
+
+        let memids = delta_encode(members.iter().map(|member| member.id))?;
+        let types = members.iter().map(|member| member.member_type as i32).collect();
+
+        let (info, roles_sid, keys, vals) = self.interning(|builder| {
+            let info = builder.encode_metadata(metadata)?;
+            let roles_sid = members
+                .iter()
+                .map(|member| to_dense_index(builder.intern(member.role)))
+                .collect::<Result<_, _>>()?;
+            let (keys, vals) = builder.intern_tags(tags);
+            Ok((info, roles_sid, keys, vals))
+        })?;
+
+        self.relations.push(pbf::Relation {
+            id,
+            keys,
+            vals,
+            info,
+            roles_sid,
+            memids,
+            types,
+        });
+
+        Ok(())
+    }
+
+    /// Builds a primitive block from the elements added since the last build, and resets the builder.
+    ///
+    /// Dense nodes, ways and relations are stored in separate primitive groups, in this order. Empty groups are
+    /// omitted.
+    pub fn build(&mut self) -> pbf::PrimitiveBlock {
+        let mut primitivegroup = Vec::new();
+
+        if !self.dense_nodes.is_empty() {
+            primitivegroup.push(pbf::PrimitiveGroup {
+                dense: Some(std::mem::take(&mut self.dense_nodes).finish()),
+                ..Default::default()
+            });
+        }
+
+        if !self.ways.is_empty() {
+            primitivegroup.push(pbf::PrimitiveGroup {
+                ways: std::mem::take(&mut self.ways),
+                ..Default::default()
+            });
+        }
+
+        if !self.relations.is_empty() {
+            primitivegroup.push(pbf::PrimitiveGroup {
+                relations: std::mem::take(&mut self.relations),
+                ..Default::default()
+            });
+        }
+
+        let stringtable = pbf::StringTable {
+            s: std::mem::take(&mut self.string_table),
+        };
+        self.reset_string_table();
+
+        pbf::PrimitiveBlock {
+            stringtable,
+            primitivegroup,
+            granularity: Some(self.options.granularity),
+            lat_offset: Some(self.options.lat_offset),
+            lon_offset: Some(self.options.lon_offset),
+            date_granularity: Some(self.options.date_granularity),
+        }
+    }
+
+    fn check_full(&self) -> Result<(), Error> {
+        if self.is_full() {
+            Err(Error::LogicError(format!(
+                "primitive block is full ({} elements)",
+                self.options.max_element_count
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn reset_string_table(&mut self) {
+        self.string_table = new_string_table();
+        self.string_indices.clear();
+    }
+
+    /// Runs `intern`, removing the strings it has interned if it fails, so rejected elements don't leave unreferenced
+    /// strings behind. Everything else should be encoded before.
+    fn interning<T>(&mut self, intern: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        let string_count = self.string_table.len();
+
+        let result = intern(self);
+        if result.is_err() {
+            self.string_table.truncate(string_count);
+            self.string_indices.retain(|_, index| (*index as usize) < string_count);
+        }
+
+        result
+    }
+
+    fn intern(&mut self, string: &str) -> u32 {
+        if string.is_empty() {
+            return 0;
+        }
+
+        if let Some(index) = self.string_indices.get(string) {
+            return *index;
+        }
+
+        let index = self.string_table.len() as u32;
+        self.string_table.push(string.as_bytes().to_vec());
+        self.string_indices.insert(string.to_string(), index);
+        index
+    }
+
+    fn intern_tags<'t>(&mut self, tags: impl IntoIterator<Item = (&'t str, &'t str)>) -> (Vec<u32>, Vec<u32>) {
+        tags.into_iter()
+            .map(|(key, value)| (self.intern(key), self.intern(value)))
+            .unzip()
+    }
+
+    /// Encodes `metadata`, interning the user name only if the rest of the metadata can be encoded.
+    fn encode_metadata(&mut self, metadata: Option<&Metadata<'_>>) -> Result<Option<pbf::Info>, Error> {
+        let Some(metadata) = metadata else {
+            return Ok(None);
+        };
+
+        let timestamp = match metadata.timestamp {
+            Some(timestamp) => Some(encode_timestamp(timestamp, self.options.date_granularity)?),
+            None => None,
+        };
+
+        Ok(Some(pbf::Info {
+            version: metadata.version,
+            timestamp,
+            changeset: metadata.changeset,
+            uid: metadata.uid,
+            user_sid: metadata.user.map(|user| self.intern(user)),
+            visible: metadata.visible,
+        }))
+    }
+}
+
+/// Encodes a coordinate in nanodegrees to the granularity grid of a block, rounding it to the nearest grid point
+/// which decodes within ±`max`.
+fn encode_coord(coord: i64, offset: i64, granularity: i32, max: i64) -> Result<i64, Error> {
+    let granularity = granularity as i128;
+    let relative_coord = coord as i128 - offset as i128;
+
+    let mut encoded = (2 * relative_coord + granularity).div_euclid(2 * granularity);
+
+    // The nearest grid point may be out of range if the offset isn't a multiple of the granularity
+    let decoded = encoded * granularity + offset as i128;
+    if decoded > max as i128 {
+        encoded -= 1;
+    } else if decoded < -max as i128 {
+        encoded += 1;
+    }
+
+    encoded
+        .try_into()
+        .map_err(|_err| Error::LogicError(format!("coordinate {coord} can't be encoded with offset {offset}")))
+}
+
+/// Encodes a timestamp in units of `date_granularity` milliseconds, rounding it down.
+fn encode_timestamp(timestamp: SystemTime, date_granularity: i32) -> Result<i64, Error> {
+    let millis = match timestamp.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_millis() as i128,
+        Err(error) => -(error.duration().as_millis() as i128),
+    };
+
+    millis
+        .div_euclid(date_granularity as i128)
+        .try_into()
+        .map_err(|_err| Error::LogicError(format!("timestamp {timestamp:?} can't be encoded")))
+}
+
+fn to_dense_index(index: u32) -> Result<i32, Error> {
+    index
+        .try_into()
+        .map_err(|_err| Error::LogicError(format!("string table index {index} can't be encoded")))
+}
+
+/// Delta encodes `values`, the inverse of [`DeltaValueReader`](crate::DeltaValueReader).
+fn delta_encode(values: impl IntoIterator<Item = i64>) -> Result<Vec<i64>, Error> {
+    let mut previous = 0i64;
+
+    values
+        .into_iter()
+        .map(|value| {
+            let delta = value
+                .checked_sub(previous)
+                .ok_or_else(|| Error::LogicError(format!("delta encoding {value} after {previous} overflows")))?;
+            previous = value;
+            Ok(delta)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod primitive_block_builder_tests {
+    use super::*;
+    use crate::element::{Element, PrimitiveBlockExt};
+
+    use std::time::Duration;
+
+    fn location(lat: i64, lon: i64) -> LatLon {
+        LatLon::new(lat, lon).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let options = BuilderOptions {
+            lat_offset: 30,
+            ..Default::default()
+        };
+        let mut builder = PrimitiveBlockBuilder::with_options(options);
+
+        let metadata = Metadata {
+            version: Some(2),
+            timestamp: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            changeset: Some(123),
+            uid: Some(42),
+            user: Some("mapper"),
+            visible: None,
+        };
+
+        builder
+            .add_node(5, location(1030, -2000), [("highway", "crossing")], Some(&metadata))
+            .unwrap();
+        builder.add_node(3, location(-949, 2000), [], None).unwrap();
+        builder
+            .add_way(10, [5, 3, 5], [("highway", "footway"), ("name", "mapper")], None)
+            .unwrap();
+        builder
+            .add_relation(
+                20,
+                [
+                    Member {
+                        id: 10,
+                        member_type: pbf::relation::MemberType::Way,
+                        role: "outer",
+                    },
+                    Member {
+                        id: 5,
+                        member_type: pbf::relation::MemberType::Node,
+                        role: "",
+                    },
+                ],
+                [("type", "multipolygon")],
+                Some(&metadata),
+            )
+            .unwrap();
+        assert_eq!(builder.len(), 4);
+
+        let block = builder.build();
+        assert!(builder.is_empty());
+        assert_eq!(block.stringtable.s[0], b"");
+        assert_eq!(block.primitivegroup.len(), 3);
+
+        let elements: Vec<_> = block.elements().collect::<Result<_, _>>().unwrap();
+        assert_eq!(elements.iter().map(Element::id).collect::<Vec<_>>(), [5, 3, 10, 20]);
+
+        let Element::Node(node) = &elements[0] else {
+            panic!("expected a node");
+        };
+        assert_eq!((node.lat().unwrap(), node.lon().unwrap()), (1030, -2000));
+        assert!(matches!(node.tags().next(), Some((Ok("highway"), Ok("crossing")))));
+        assert_eq!(node.metadata().unwrap().unwrap(), metadata);
+
+        let Element::Node(node) = &elements[1] else {
+            panic!("expected a node");
+        };
+        assert_eq!((node.lat().unwrap(), node.lon().unwrap()), (-970, 2000));
+        assert_eq!(node.tags().count(), 0);
+
+        let Element::Way(way) = &elements[2] else {
+            panic!("expected a way");
+        };
+        assert_eq!(way.refs().collect::<Result<Vec<_>, _>>().unwrap(), [5, 3, 5]);
+        assert_eq!(way.tags().count(), 2);
+        assert!(way.metadata().is_none());
+
+        let Element::Relation(relation) = &elements[3] else {
+            panic!("expected a relation");
+        };
+        let members: Vec<_> = relation.members().unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(members[0].role, "outer");
+        assert_eq!(members[1].id, 5);
+        assert_eq!(relation.metadata().unwrap().unwrap(), metadata);
+
+        // "mapper" is interned once
+        let mapper_count = block.stringtable.s.iter().filter(|s| *s == b"mapper").count();
+        assert_eq!(mapper_count, 1);
+    }
+
+    #[test]
+    fn max_element_count() {
+        let options = BuilderOptions {
+            max_element_count: 2,
+            ..Default::default()
+        };
+        let mut builder = PrimitiveBlockBuilder::with_options(options);
+
+        builder.add_node(1, location(0, 0), [], None).unwrap();
+        builder.add_way(2, [1], [], None).unwrap();
+        assert!(builder.is_full());
+        assert!(matches!(
+            builder.add_relation(3, [], [], None),
+            Err(Error::LogicError(_))
+        ));
+
+        let block = builder.build();
+        assert!(block.primitivegroup[0].dense.as_ref().unwrap().keys_vals.is_empty());
+        assert!(!builder.is_full());
+    }
+
+    #[test]
+    fn rejected_elements() {
+        let metadata = Metadata {
+            version: None,
+            timestamp: None,
+            changeset: None,
+            uid: None,
+            user: Some("rejected"),
+            visible: None,
+        };
+        let tags = [("highway", "rejected")];
+        let member = |id| Member {
+            id,
+            member_type: pbf::relation::MemberType::Node,
+            role: "rejected",
+        };
+
+        let mut builder = PrimitiveBlockBuilder::new();
+        builder
+            .add_node(i64::MAX, location(0, 0), [("highway", "crossing")], None)
+            .unwrap();
+
+        // Delta encoding the IDs overflows
+        assert!(builder.add_node(-2, location(0, 0), tags, Some(&metadata)).is_err());
+        assert!(builder.add_way(1, [i64::MAX, -2], tags, Some(&metadata)).is_err());
+        assert!(builder
+            .add_relation(2, [member(i64::MAX), member(-2)], tags, Some(&metadata))
+            .is_err());
+
+        builder
+            .add_way(3, [i64::MAX], [("highway", "residential")], None)
+            .unwrap();
+
+        let block = builder.build();
+        assert_eq!(block.stringtable.s, [&b""[..], b"highway", b"crossing", b"residential"]);
+    }
+    #[test]
+    fn empty_strings() {
+        let mut builder = PrimitiveBlockBuilder::new();
+        builder.add_node(1, location(0, 0), [("note", "")], None).unwrap();
+        builder.add_way(2, [1], [("", "empty")], None).unwrap();
+
+        let block = builder.build();
+        assert_eq!(block.stringtable.s, [&b""[..], b"note", b"empty"]);
+        assert_eq!(block.primitivegroup[0].dense.as_ref().unwrap().keys_vals, [1, 0, 0]);
+        assert_eq!(block.primitivegroup[1].ways[0].keys, [0]);
+    }
+
+    #[test]
+    fn coords_at_bounds() {
+        let options = BuilderOptions {
+            lat_offset: 30,
+            lon_offset: -30,
+            ..Default::default()
+        };
+        let mut builder = PrimitiveBlockBuilder::with_options(options);
+
+        // The nearest grid points of the first node are 30 nanodegrees out of range
+        builder
+            .add_node(1, location(LatLon::MAX_LAT, -LatLon::MAX_LON), [], None)
+            .unwrap();
+        builder
+            .add_node(2, location(-LatLon::MAX_LAT, LatLon::MAX_LON), [], None)
+            .unwrap();
+
+        let block = builder.build();
+        let locations: Vec<_> = block
+            .elements()
+            .map(|element| match element.unwrap() {
+                Element::Node(node) => node.location().unwrap(),
+                _ => panic!("expected a node"),
+            })
+            .collect();
+
+        assert_eq!(locations[0].lat(), 89_999_999_930);
+        assert_eq!(locations[0].lon(), -179_999_999_930);
+        assert_eq!(locations[1].lat(), -89_999_999_970);
+        assert_eq!(locations[1].lon(), 179_999_999_970);
+    }
+}
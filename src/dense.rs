@@ -1,32 +1,33 @@
 //! Helpers for reading dense nodes.
 
-use crate::{pbf, Error, TagReader};
+use crate::{pbf, Error, TagIndices, TagReader};
 
 use std::iter::{Enumerate, Zip};
 use std::ops::AddAssign;
 use std::slice::Iter;
 
 /// An unpacked dense node, returned when iterating on [`DenseNodeReader`].
+#[derive(Debug, Clone, PartialEq)]
 pub struct DenseNode<'a> {
     pub id: i64,
 
     /// Latitude of the node in an encoded format.
-    /// Use [`util::normalize_coord`] to convert it to nanodegrees.
+    /// Use [`util::normalize_coord`](crate::util::normalize_coord) to convert it to nanodegrees.
     pub lat: i64,
 
     /// Longitude of the node in an encoded format.
-    /// Use [`util::normalize_coord`] to convert it to nanodegrees.
+    /// Use [`util::normalize_coord`](crate::util::normalize_coord) to convert it to nanodegrees.
     pub lon: i64,
 
     /// Optional metadata.
     pub info: Option<pbf::Info>,
 
     /// Key/value index slice of [`pbf::DenseNodes::keys_vals`]. Indices point into a [`pbf::StringTable`].
-    /// Use [`DenseTagReader`] to read these key/value pairs conveniently.
+    /// Use [`new_dense_tag_reader`] to read these key/value pairs conveniently.
     pub key_value_indices: &'a [i32],
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy)]
 struct DeltaCodedValues {
     id: i64,
     lat: i64,
@@ -192,17 +193,176 @@ pub fn new_dense_tag_reader<'a>(
 ) -> TagReader<'a, impl Iterator<Item = (Result<usize, Error>, Result<usize, Error>)> + 'a> {
     TagReader {
         string_table,
-        iter: key_value_indices.chunks_exact(2).map(|s| {
-            let convert_idx = |index: i32| -> Result<usize, Error> {
-                if let Ok(index) = TryInto::<usize>::try_into(index) {
-                    Ok(index)
-                } else {
-                    Err(Error::LogicError(format!("string table index {index} is invalid")))
-                }
-            };
+        iter: TagIndices::dense(key_value_indices),
+    }
+}
 
-            (convert_idx(s[0]), convert_idx(s[1]))
-        }),
+/// Utility for delta encoding dense nodes, the inverse of [`DenseNodeReader`].
+///
+/// Since dense node metadata is stored in parallel arrays, a metadata field is either stored for all nodes, or for
+/// none of them. If only some nodes have a field, the others get the field's default value (`-1` for versions and
+/// `true` for visibility), or the value of the previous node for delta encoded fields. Nodes without metadata are
+/// treated as having none of the fields. Hence [`DenseNodeReader`] only reads back the same nodes if all or none of
+/// them have each metadata field.
+#[derive(Default)]
+pub struct DenseNodeWriter {
+    dense_nodes: pbf::DenseNodes,
+    has_tags: bool,
+    current: DeltaCodedValues, // Current values of delta coded fields
+}
+
+/// Appends `value` to the values of a dense info field, or the field's default if `value` is `None`. Values are
+/// only stored once a node has the field, so fields missing from all nodes are omitted.
+fn push_dense_info_field<T: Copy>(values: &mut Vec<T>, node_count: usize, value: Option<T>, default: T) {
+    match value {
+        Some(value) => {
+            values.resize(node_count, default);
+            values.push(value);
+        }
+        None if !values.is_empty() => values.push(default),
+        None => {}
+    }
+}
+
+impl DenseNodeWriter {
+    /// Constructs a new, empty `DenseNodeWriter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rosm_pbf_reader::dense::{DenseNodeReader, DenseNodeWriter};
+    ///
+    /// let mut writer = DenseNodeWriter::new();
+    /// writer.push(10, 4_750_000, 1_904_000, &[1, 2], None).unwrap();
+    /// writer.push(11, 4_750_010, 1_904_020, &[], None).unwrap();
+    ///
+    /// let dense_nodes = writer.finish();
+    /// assert_eq!(dense_nodes.id, [10, 1]);
+    ///
+    /// let ids: Vec<_> = DenseNodeReader::new(&dense_nodes).unwrap().map(|node| node.unwrap().id).collect();
+    /// assert_eq!(ids, [10, 11]);
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of nodes written.
+    pub fn len(&self) -> usize {
+        self.dense_nodes.id.len()
+    }
+
+    /// Returns whether no nodes have been written.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends a node with encoded coordinates (like [`DenseNode::lat`] and [`DenseNode::lon`]), key/value string
+    /// table indices (like [`DenseNode::key_value_indices`]) and optional metadata.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `key_value_indices` has an odd length or contains a key index of 0, or delta encoding
+    /// a value overflows. Nothing is appended in this case.
+    pub fn push(
+        &mut self,
+        id: i64,
+        lat: i64,
+        lon: i64,
+        key_value_indices: &[i32],
+        info: Option<&pbf::Info>,
+    ) -> Result<(), Error> {
+        #[allow(clippy::manual_is_multiple_of)] // `usize::is_multiple_of` requires Rust 1.87
+        if key_value_indices.len() % 2 != 0 {
+            return Err(Error::LogicError(format!(
+                "dense node key/value index count is odd: {}",
+                key_value_indices.len()
+            )));
+        }
+
+        if key_value_indices.iter().step_by(2).any(|key_idx| *key_idx == 0) {
+            return Err(Error::LogicError(
+                "dense node key index 0 is reserved as a delimiter".to_string(),
+            ));
+        }
+
+        let overflow = |field: &str| Error::LogicError(format!("delta encoding dense node `{field}` overflows"));
+
+        let id_delta = id.checked_sub(self.current.id).ok_or_else(|| overflow("id"))?;
+        let lat_delta = lat.checked_sub(self.current.lat).ok_or_else(|| overflow("lat"))?;
+        let lon_delta = lon.checked_sub(self.current.lon).ok_or_else(|| overflow("lon"))?;
+
+        let info = info.copied().unwrap_or_default();
+
+        let timestamp_delta = match info.timestamp {
+            Some(timestamp) => Some(
+                timestamp
+                    .checked_sub(self.current.timestamp)
+                    .ok_or_else(|| overflow("timestamp"))?,
+            ),
+            None => None,
+        };
+        let changeset_delta = match info.changeset {
+            Some(changeset) => Some(
+                changeset
+                    .checked_sub(self.current.changeset)
+                    .ok_or_else(|| overflow("changeset"))?,
+            ),
+            None => None,
+        };
+        let uid_delta = match info.uid {
+            Some(uid) => Some(uid.checked_sub(self.current.uid).ok_or_else(|| overflow("uid"))?),
+            None => None,
+        };
+        let user_sid_delta: Option<i32> = match info.user_sid {
+            Some(user_sid) => Some(
+                (user_sid as i64 - self.current.user_sid as i64)
+                    .try_into()
+                    .map_err(|_err| overflow("user_sid"))?,
+            ),
+            None => None,
+        };
+
+        let node_count = self.len();
+
+        self.dense_nodes.id.push(id_delta);
+        self.dense_nodes.lat.push(lat_delta);
+        self.dense_nodes.lon.push(lon_delta);
+
+        self.dense_nodes.keys_vals.extend_from_slice(key_value_indices);
+        self.dense_nodes.keys_vals.push(0);
+        self.has_tags |= !key_value_indices.is_empty();
+
+        if info != pbf::Info::default() || self.dense_nodes.denseinfo.is_some() {
+            let dense_info = self.dense_nodes.denseinfo.get_or_insert_with(Default::default);
+
+            push_dense_info_field(&mut dense_info.version, node_count, info.version, -1);
+            push_dense_info_field(&mut dense_info.timestamp, node_count, timestamp_delta, 0);
+            push_dense_info_field(&mut dense_info.changeset, node_count, changeset_delta, 0);
+            push_dense_info_field(&mut dense_info.uid, node_count, uid_delta, 0);
+            push_dense_info_field(&mut dense_info.user_sid, node_count, user_sid_delta, 0);
+            push_dense_info_field(&mut dense_info.visible, node_count, info.visible, true);
+        }
+
+        self.current = DeltaCodedValues {
+            id,
+            lat,
+            lon,
+            timestamp: info.timestamp.unwrap_or(self.current.timestamp),
+            changeset: info.changeset.unwrap_or(self.current.changeset),
+            uid: info.uid.unwrap_or(self.current.uid),
+            user_sid: info.user_sid.unwrap_or(self.current.user_sid),
+        };
+
+        Ok(())
+    }
+
+    /// Returns the encoded dense nodes. Key/value indices are omitted if no node has tags.
+    pub fn finish(mut self) -> pbf::DenseNodes {
+        if !self.has_tags {
+            self.dense_nodes.keys_vals.clear();
+        }
+
+        self.dense_nodes
     }
 }
 
@@ -300,3 +460,122 @@ mod dense_node_reader_tests {
         assert!(next.unwrap().is_err());
     }
 }
+
+#[cfg(test)]
+mod dense_node_writer_tests {
+    use super::*;
+
+    /// Minimal xorshift generator, to generate reproducible test inputs without extra dependencies.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, bound: u64) -> u64 {
+            self.next() % bound
+        }
+
+        fn value(&mut self, magnitude: i64) -> i64 {
+            (self.below(2 * magnitude as u64 + 1) as i64) - magnitude
+        }
+    }
+
+    type Node = (i64, i64, i64, Vec<i32>, Option<pbf::Info>);
+
+    fn random_nodes(rng: &mut Rng) -> Vec<Node> {
+        // Each input either has no metadata, or the same, non-empty set of metadata fields for all nodes
+        let field_mask = if rng.below(4) == 0 {
+            None
+        } else {
+            Some(rng.below(63) + 1)
+        };
+        let field = |mask: u64, bit: u64| mask & (1 << bit) != 0;
+
+        (0..rng.below(50))
+            .map(|_| {
+                let tag_count = rng.below(4) as usize;
+                let key_value_indices = (0..2 * tag_count).map(|_| rng.below(1000) as i32 + 1).collect();
+
+                let info = field_mask.map(|mask| pbf::Info {
+                    version: field(mask, 0).then(|| rng.below(100) as i32),
+                    timestamp: field(mask, 1).then(|| rng.value(1 << 40)),
+                    changeset: field(mask, 2).then(|| rng.value(1 << 40)),
+                    uid: field(mask, 3).then(|| rng.value(1 << 30) as i32),
+                    user_sid: field(mask, 4).then(|| rng.below(1 << 30) as u32),
+                    visible: field(mask, 5).then(|| rng.below(2) == 0),
+                });
+
+                (
+                    rng.value(1 << 50),
+                    rng.value(900_000_000),
+                    rng.value(1_800_000_000),
+                    key_value_indices,
+                    info,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..500 {
+            let nodes = random_nodes(&mut rng);
+
+            let mut writer = DenseNodeWriter::new();
+            for (id, lat, lon, key_value_indices, info) in &nodes {
+                writer.push(*id, *lat, *lon, key_value_indices, info.as_ref()).unwrap();
+            }
+            let dense_nodes = writer.finish();
+
+            let decoded_nodes: Vec<_> = DenseNodeReader::new(&dense_nodes)
+                .unwrap()
+                .map(|node| {
+                    let node = node.unwrap();
+                    (node.id, node.lat, node.lon, node.key_value_indices.to_vec(), node.info)
+                })
+                .collect();
+
+            assert_eq!(decoded_nodes, nodes);
+        }
+    }
+
+    #[test]
+    fn partial_metadata() {
+        let info = pbf::Info {
+            version: Some(3),
+            timestamp: Some(100),
+            ..Default::default()
+        };
+
+        let mut writer = DenseNodeWriter::new();
+        writer.push(1, 0, 0, &[], None).unwrap();
+        writer.push(2, 0, 0, &[], Some(&info)).unwrap();
+        writer.push(3, 0, 0, &[], None).unwrap();
+        let dense_nodes = writer.finish();
+
+        let dense_info = dense_nodes.denseinfo.as_ref().unwrap();
+        assert_eq!(dense_info.version, [-1, 3, -1]);
+        assert_eq!(dense_info.timestamp, [0, 100, 0]);
+        assert!(dense_info.uid.is_empty());
+        assert!(dense_info.visible.is_empty());
+        assert!(dense_nodes.keys_vals.is_empty());
+    }
+
+    #[test]
+    fn invalid_input() {
+        let mut writer = DenseNodeWriter::new();
+        assert!(writer.push(1, 0, 0, &[1], None).is_err());
+        assert!(writer.push(1, 0, 0, &[0, 1], None).is_err());
+        writer.push(i64::MIN, 0, 0, &[1, 0], None).unwrap();
+        assert!(writer.push(1, 0, 0, &[], None).is_err());
+        assert_eq!(writer.len(), 1);
+        assert_eq!(writer.finish().keys_vals, [1, 0, 0]);
+    }
+}
@@ -0,0 +1,975 @@
+//! Borrowed, high-level views of the nodes, ways and relations of primitive blocks.
+//!
+//! Nodes are represented the same way, regardless of whether they are stored as normal or dense nodes.
+
+use crate::dense::{DenseNode, DenseNodeReader};
+use crate::util::{checked_normalize_coord, system_time_from_millis, LatLon};
+use crate::{decode_string, pbf, DeltaValueReader, Error, TagIndices, TagReader};
+
+use std::iter::Zip;
+use std::slice::Iter;
+use std::time::SystemTime;
+
+/// A node, way or relation of a [`pbf::PrimitiveBlock`].
+#[derive(Debug, Clone)]
+pub enum Element<'a> {
+    /// A normal or dense node.
+    Node(Node<'a>),
+    /// A way.
+    Way(Way<'a>),
+    /// A relation.
+    Relation(Relation<'a>),
+}
+
+impl<'a> Element<'a> {
+    /// Returns the ID of the element.
+    pub fn id(&self) -> i64 {
+        match self {
+            Element::Node(node) => node.id(),
+            Element::Way(way) => way.id(),
+            Element::Relation(relation) => relation.id(),
+        }
+    }
+
+    /// Returns a reader of the tags of the element.
+    pub fn tags(&self) -> TagReader<'a, TagIndices<'a>> {
+        match self {
+            Element::Node(node) => node.tags(),
+            Element::Way(way) => way.tags(),
+            Element::Relation(relation) => relation.tags(),
+        }
+    }
+
+    /// Returns the optional metadata of the element.
+    pub fn info(&self) -> Option<&pbf::Info> {
+        match self {
+            Element::Node(node) => node.info(),
+            Element::Way(way) => way.info(),
+            Element::Relation(relation) => relation.info(),
+        }
+    }
+
+    /// Returns the optional metadata of the element, decoded into a [`Metadata`].
+    pub fn metadata(&self) -> Option<Result<Metadata<'a>, Error>> {
+        match self {
+            Element::Node(node) => node.metadata(),
+            Element::Way(way) => way.metadata(),
+            Element::Relation(relation) => relation.metadata(),
+        }
+    }
+}
+
+/// A node, read either from [`pbf::PrimitiveGroup::nodes`] or [`pbf::PrimitiveGroup::dense`].
+#[derive(Clone)]
+pub struct Node<'a> {
+    id: i64,
+    lat: i64, // Encoded latitude
+    lon: i64, // Encoded longitude
+    info: Option<pbf::Info>,
+    block: &'a pbf::PrimitiveBlock,
+    tag_indices: TagIndices<'a>,
+}
+
+impl<'a> Node<'a> {
+    /// Constructs a node view from a normal node of `block`. See also [`NodeReader`].
+    pub fn from_sparse(node: &'a pbf::Node, block: &'a pbf::PrimitiveBlock) -> Self {
+        Node {
+            id: node.id,
+            lat: node.lat,
+            lon: node.lon,
+            info: node.info,
+            block,
+            tag_indices: TagIndices::sparse(&node.keys, &node.vals),
+        }
+    }
+
+    /// Constructs a node view from a dense node of `block`, read by [`DenseNodeReader`].
+    pub fn from_dense(node: DenseNode<'a>, block: &'a pbf::PrimitiveBlock) -> Self {
+        Node {
+            id: node.id,
+            lat: node.lat,
+            lon: node.lon,
+            info: node.info,
+            block,
+            tag_indices: TagIndices::dense(node.key_value_indices),
+        }
+    }
+
+    /// Returns the ID of the node.
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Returns the latitude of the node in nanodegrees.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the location of the node is invalid. See [`Node::location`].
+    pub fn lat(&self) -> Result<i64, Error> {
+        self.location().map(|location| location.lat())
+    }
+
+    /// Returns the longitude of the node in nanodegrees.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the location of the node is invalid. See [`Node::location`].
+    pub fn lon(&self) -> Result<i64, Error> {
+        self.location().map(|location| location.lon())
+    }
+
+    /// Returns the validated location of the node.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if normalizing the coordinate overflows, or results in an out of range coordinate.
+    pub fn location(&self) -> Result<LatLon, Error> {
+        checked_normalize_coord(self.lat, self.lon, self.block)
+    }
+
+    /// Returns the optional metadata of the node.
+    pub fn info(&self) -> Option<&pbf::Info> {
+        self.info.as_ref()
+    }
+
+    /// Returns the optional metadata of the node, decoded into a [`Metadata`].
+    pub fn metadata(&self) -> Option<Result<Metadata<'a>, Error>> {
+        self.info.as_ref().map(|info| Metadata::new(info, self.block))
+    }
+
+    /// Returns a reader of the tags of the node.
+    pub fn tags(&self) -> TagReader<'a, TagIndices<'a>> {
+        TagReader {
+            string_table: &self.block.stringtable,
+            iter: self.tag_indices.clone(),
+        }
+    }
+}
+
+// The primitive block is omitted, it would be printed for every element
+impl std::fmt::Debug for Node<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Node")
+            .field("id", &self.id)
+            .field("lat", &self.lat)
+            .field("lon", &self.lon)
+            .field("info", &self.info)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Metadata of an element, decoded from a [`pbf::Info`].
+///
+/// `chrono::DateTime<Utc>` and `time::OffsetDateTime` both implement `From<SystemTime>`, so the timestamp can be
+/// converted to them if needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata<'a> {
+    /// Version of the element.
+    pub version: Option<i32>,
+    /// Time of the last modification of the element.
+    pub timestamp: Option<SystemTime>,
+    /// ID of the changeset which last modified the element.
+    pub changeset: Option<i64>,
+    /// ID of the user who last modified the element.
+    pub uid: Option<i32>,
+    /// Name of the user who last modified the element.
+    pub user: Option<&'a str>,
+    /// Whether the element is visible, or has been deleted. Only set in files with historical information.
+    pub visible: Option<bool>,
+}
+
+impl<'a> Metadata<'a> {
+    /// Decodes `info` of an element of `block`, resolving its user name and converting its timestamp.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the user name can't be read from the string table, or the timestamp is out of range.
+    pub fn new(info: &pbf::Info, block: &'a pbf::PrimitiveBlock) -> Result<Self, Error> {
+        let timestamp = match info.timestamp {
+            Some(timestamp) => Some(normalize_system_time(timestamp, block)?),
+            None => None,
+        };
+
+        let user = match info.user_sid {
+            Some(user_sid) => Some(decode_string(&block.stringtable, user_sid as usize)?),
+            None => None,
+        };
+
+        Ok(Metadata {
+            version: info.version,
+            timestamp,
+            changeset: info.changeset,
+            uid: info.uid,
+            user,
+            visible: info.visible,
+        })
+    }
+}
+
+/// Converts an encoded timestamp of `block` to a `SystemTime`.
+fn normalize_system_time(timestamp: i64, block: &pbf::PrimitiveBlock) -> Result<SystemTime, Error> {
+    timestamp
+        .checked_mul(block.date_granularity() as i64)
+        .and_then(system_time_from_millis)
+        .ok_or_else(|| Error::LogicError(format!("timestamp {timestamp} is out of range")))
+}
+
+/// Utility for reading normal nodes, like [`pbf::PrimitiveGroup::nodes`], as the same [`Node`] views as dense nodes.
+pub struct NodeReader<'a> {
+    block: &'a pbf::PrimitiveBlock,
+    nodes: Iter<'a, pbf::Node>,
+}
+
+impl<'a> NodeReader<'a> {
+    /// Constructs a new `NodeReader` from a slice of nodes, and their primitive block.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::element::{Node, NodeReader};
+    /// use rosm_pbf_reader::dense::DenseNodeReader;
+    /// use rosm_pbf_reader::{pbf, Error};
+    ///
+    /// fn process_node(node: Node) -> Result<(), Error> {
+    ///     let location = node.location()?;
+    ///     println!("node {} at {}, {}", node.id(), location.lat_degrees(), location.lon_degrees());
+    ///     Ok(())
+    /// }
+    ///
+    /// fn process_primitive_block(block: pbf::PrimitiveBlock) -> Result<(), Error> {
+    ///     for group in &block.primitivegroup {
+    ///         NodeReader::new(&group.nodes, &block).try_for_each(process_node)?;
+    ///
+    ///         if let Some(dense_nodes) = &group.dense {
+    ///             for node in DenseNodeReader::new(dense_nodes)? {
+    ///                 process_node(Node::from_dense(node?, &block))?;
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn new(nodes: &'a [pbf::Node], block: &'a pbf::PrimitiveBlock) -> Self {
+        NodeReader {
+            block,
+            nodes: nodes.iter(),
+        }
+    }
+}
+
+impl<'a> Iterator for NodeReader<'a> {
+    type Item = Node<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.nodes.next().map(|node| Node::from_sparse(node, self.block))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+}
+
+impl ExactSizeIterator for NodeReader<'_> {}
+
+/// A way of a primitive block.
+#[derive(Clone, Copy)]
+pub struct Way<'a> {
+    way: &'a pbf::Way,
+    block: &'a pbf::PrimitiveBlock,
+}
+
+impl<'a> Way<'a> {
+    /// Constructs a way view from a way of `block`.
+    pub fn new(way: &'a pbf::Way, block: &'a pbf::PrimitiveBlock) -> Self {
+        Way { way, block }
+    }
+
+    /// Returns the ID of the way.
+    pub fn id(&self) -> i64 {
+        self.way.id
+    }
+
+    /// Returns the optional metadata of the way.
+    pub fn info(&self) -> Option<&'a pbf::Info> {
+        self.way.info.as_ref()
+    }
+
+    /// Returns the optional metadata of the way, decoded into a [`Metadata`].
+    pub fn metadata(&self) -> Option<Result<Metadata<'a>, Error>> {
+        self.way.info.as_ref().map(|info| Metadata::new(info, self.block))
+    }
+
+    /// Returns a reader of the tags of the way.
+    pub fn tags(&self) -> TagReader<'a, TagIndices<'a>> {
+        TagReader {
+            string_table: &self.block.stringtable,
+            iter: TagIndices::sparse(&self.way.keys, &self.way.vals),
+        }
+    }
+
+    /// Returns an iterator over the IDs of the nodes of the way, which returns an error if decoding an ID overflows.
+    pub fn refs(&self) -> DeltaValueReader<'a, i64> {
+        DeltaValueReader::new(&self.way.refs)
+    }
+
+    /// Returns a reader of the IDs and locations of the nodes of the way, if the file has the `LocationsOnWays`
+    /// optional feature.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the node ID, latitude and longitude counts of the way do not match.
+    pub fn locations(&self) -> Result<WayNodeReader<'a>, Error> {
+        WayNodeReader::new(self.way, self.block)
+    }
+
+    /// Returns the raw way.
+    pub fn raw(&self) -> &'a pbf::Way {
+        self.way
+    }
+}
+
+impl std::fmt::Debug for Way<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Way").field("way", self.way).finish_non_exhaustive()
+    }
+}
+
+/// A node of a way with its location, returned when iterating on [`WayNodeReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WayNode {
+    /// ID of the node.
+    pub id: i64,
+    /// Validated location of the node.
+    pub location: LatLon,
+}
+
+/// Utility for reading the node IDs and locations of a [`pbf::Way`], stored in files with the `LocationsOnWays`
+/// optional feature. Allows building way geometries without looking up their nodes.
+pub struct WayNodeReader<'a> {
+    block: &'a pbf::PrimitiveBlock,
+    refs: DeltaValueReader<'a, i64>,
+    lats: DeltaValueReader<'a, i64>,
+    lons: DeltaValueReader<'a, i64>,
+}
+
+impl<'a> WayNodeReader<'a> {
+    /// Constructs a new `WayNodeReader` from a way, and its primitive block.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::element::WayNodeReader;
+    /// use rosm_pbf_reader::{pbf, Error};
+    ///
+    /// fn process_primitive_block(block: pbf::PrimitiveBlock) -> Result<(), Error> {
+    ///     for group in &block.primitivegroup {
+    ///         for way in &group.ways {
+    ///             for node in WayNodeReader::new(way, &block)? {
+    ///                 let node = node?;
+    ///                 println!("node {} at {:?}", node.id, node.location);
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the node ID, latitude and longitude counts in `way` do not match, for example because
+    /// the way has no locations.
+    pub fn new(way: &'a pbf::Way, block: &'a pbf::PrimitiveBlock) -> Result<Self, Error> {
+        if way.lat.len() != way.refs.len() || way.lon.len() != way.refs.len() {
+            Err(Error::LogicError(format!(
+                "way node id/lat/lon counts differ: {}/{}/{}",
+                way.refs.len(),
+                way.lat.len(),
+                way.lon.len()
+            )))
+        } else {
+            Ok(WayNodeReader {
+                block,
+                refs: DeltaValueReader::new(&way.refs),
+                lats: DeltaValueReader::new(&way.lat),
+                lons: DeltaValueReader::new(&way.lon),
+            })
+        }
+    }
+}
+
+impl Iterator for WayNodeReader<'_> {
+    /// A node, or an error if decoding its ID or location has failed
+    type Item = Result<WayNode, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, lat, lon) = (self.refs.next()?, self.lats.next()?, self.lons.next()?);
+
+        let node = || -> Result<WayNode, Error> {
+            Ok(WayNode {
+                id: id?,
+                location: checked_normalize_coord(lat?, lon?, self.block)?,
+            })
+        };
+
+        Some(node())
+    }
+}
+
+/// A relation of a primitive block.
+#[derive(Clone, Copy)]
+pub struct Relation<'a> {
+    relation: &'a pbf::Relation,
+    block: &'a pbf::PrimitiveBlock,
+}
+
+/// A member of a relation, returned when iterating on [`RelationMemberReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Member<'a> {
+    /// ID of the member element.
+    pub id: i64,
+    /// Type of the member element.
+    pub member_type: pbf::relation::MemberType,
+    /// Role of the member in the relation.
+    pub role: &'a str,
+}
+
+impl<'a> Relation<'a> {
+    /// Constructs a relation view from a relation of `block`.
+    pub fn new(relation: &'a pbf::Relation, block: &'a pbf::PrimitiveBlock) -> Self {
+        Relation { relation, block }
+    }
+
+    /// Returns the ID of the relation.
+    pub fn id(&self) -> i64 {
+        self.relation.id
+    }
+
+    /// Returns the optional metadata of the relation.
+    pub fn info(&self) -> Option<&'a pbf::Info> {
+        self.relation.info.as_ref()
+    }
+
+    /// Returns the optional metadata of the relation, decoded into a [`Metadata`].
+    pub fn metadata(&self) -> Option<Result<Metadata<'a>, Error>> {
+        self.relation.info.as_ref().map(|info| Metadata::new(info, self.block))
+    }
+
+    /// Returns a reader of the tags of the relation.
+    pub fn tags(&self) -> TagReader<'a, TagIndices<'a>> {
+        TagReader {
+            string_table: &self.block.stringtable,
+            iter: TagIndices::sparse(&self.relation.keys, &self.relation.vals),
+        }
+    }
+
+    /// Returns a reader of the members of the relation.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the member ID, type and role counts of the relation do not match.
+    pub fn members(&self) -> Result<RelationMemberReader<'a>, Error> {
+        RelationMemberReader::new(self.relation, &self.block.stringtable)
+    }
+
+    /// Returns the raw relation.
+    pub fn raw(&self) -> &'a pbf::Relation {
+        self.relation
+    }
+}
+
+impl std::fmt::Debug for Relation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Relation")
+            .field("relation", self.relation)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Utility for reading the members of a [`pbf::Relation`], decoding their delta-encoded IDs, types and roles.
+pub struct RelationMemberReader<'a> {
+    string_table: &'a pbf::StringTable,
+    ids: DeltaValueReader<'a, i64>,
+    types_roles: Zip<Iter<'a, i32>, Iter<'a, i32>>, // (member_type, role_sid) iterator
+}
+
+impl<'a> RelationMemberReader<'a> {
+    /// Constructs a new `RelationMemberReader` from a relation, and the string table of its primitive block.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::element::RelationMemberReader;
+    /// use rosm_pbf_reader::{pbf, Error};
+    ///
+    /// fn process_primitive_block(block: pbf::PrimitiveBlock) -> Result<(), Error> {
+    ///     for group in &block.primitivegroup {
+    ///         for relation in &group.relations {
+    ///             for member in RelationMemberReader::new(relation, &block.stringtable)? {
+    ///                 let member = member?;
+    ///                 println!("{:?} {} as {}", member.member_type, member.id, member.role);
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the member ID, type and role counts in `relation` do not match.
+    pub fn new(relation: &'a pbf::Relation, string_table: &'a pbf::StringTable) -> Result<Self, Error> {
+        if relation.types.len() != relation.memids.len() || relation.roles_sid.len() != relation.memids.len() {
+            Err(Error::LogicError(format!(
+                "relation member id/type/role counts differ: {}/{}/{}",
+                relation.memids.len(),
+                relation.types.len(),
+                relation.roles_sid.len()
+            )))
+        } else {
+            Ok(RelationMemberReader {
+                string_table,
+                ids: DeltaValueReader::new(&relation.memids),
+                types_roles: relation.types.iter().zip(relation.roles_sid.iter()),
+            })
+        }
+    }
+}
+
+impl<'a> Iterator for RelationMemberReader<'a> {
+    /// A member, or an error if decoding its ID, type or role has failed
+    type Item = Result<Member<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = match self.ids.next()? {
+            Ok(id) => id,
+            Err(error) => return Some(Err(error)),
+        };
+        let (member_type, role_sid) = self.types_roles.next()?;
+
+        let member_type = match pbf::relation::MemberType::try_from(*member_type) {
+            Ok(member_type) => member_type,
+            Err(_) => {
+                return Some(Err(Error::LogicError(format!(
+                    "relation member type {member_type} is invalid"
+                ))))
+            }
+        };
+
+        let role = match usize::try_from(*role_sid) {
+            Ok(role_sid) => decode_string(self.string_table, role_sid),
+            Err(_) => Err(Error::LogicError(format!("string table index {role_sid} is invalid"))),
+        };
+
+        Some(role.map(|role| Member { id, member_type, role }))
+    }
+}
+
+/// Iterator over the elements of a primitive block, returned by [`PrimitiveBlockExt::elements`].
+///
+/// The elements of each primitive group are returned in the order of nodes, dense nodes, ways, then relations.
+pub struct Elements<'a> {
+    block: &'a pbf::PrimitiveBlock,
+    groups: Iter<'a, pbf::PrimitiveGroup>,
+    nodes: NodeReader<'a>,
+    dense_nodes: Option<Result<DenseNodeReader<'a>, Error>>,
+    ways: Iter<'a, pbf::Way>,
+    relations: Iter<'a, pbf::Relation>,
+}
+
+impl<'a> Elements<'a> {
+    fn new(block: &'a pbf::PrimitiveBlock) -> Self {
+        Elements {
+            block,
+            groups: block.primitivegroup.iter(),
+            nodes: NodeReader::new(&[], block),
+            dense_nodes: None,
+            ways: Iter::default(),
+            relations: Iter::default(),
+        }
+    }
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Result<Element<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(node) = self.nodes.next() {
+                return Some(Ok(Element::Node(node)));
+            }
+
+            match &mut self.dense_nodes {
+                Some(Ok(dense_nodes)) => match dense_nodes.next() {
+                    Some(node) => return Some(node.map(|node| Element::Node(Node::from_dense(node, self.block)))),
+                    None => self.dense_nodes = None,
+                },
+                Some(Err(_)) => {
+                    if let Some(Err(error)) = self.dense_nodes.take() {
+                        return Some(Err(error));
+                    }
+                }
+                None => {}
+            }
+
+            if let Some(way) = self.ways.next() {
+                return Some(Ok(Element::Way(Way::new(way, self.block))));
+            }
+
+            if let Some(relation) = self.relations.next() {
+                return Some(Ok(Element::Relation(Relation::new(relation, self.block))));
+            }
+
+            let group = self.groups.next()?;
+
+            self.nodes = NodeReader::new(&group.nodes, self.block);
+            self.dense_nodes = group.dense.as_ref().map(DenseNodeReader::new);
+            self.ways = group.ways.iter();
+            self.relations = group.relations.iter();
+        }
+    }
+}
+
+/// Extension of [`pbf::PrimitiveBlock`] to iterate its elements uniformly.
+pub trait PrimitiveBlockExt {
+    /// Returns an iterator over the nodes, ways and relations of all primitive groups of the block.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::element::{Element, PrimitiveBlockExt};
+    /// use rosm_pbf_reader::{pbf, Error};
+    ///
+    /// fn process_primitive_block(block: pbf::PrimitiveBlock) -> Result<(), Error> {
+    ///     for element in block.elements() {
+    ///         if let Element::Node(node) = element? {
+    ///             println!("node {} at {}, {}", node.id(), node.lat()?, node.lon()?);
+    ///         }
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    fn elements(&self) -> Elements<'_>;
+}
+
+impl PrimitiveBlockExt for pbf::PrimitiveBlock {
+    fn elements(&self) -> Elements<'_> {
+        Elements::new(self)
+    }
+}
+
+#[cfg(test)]
+mod element_tests {
+    use super::*;
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn test_block() -> pbf::PrimitiveBlock {
+        let strings = ["", "highway", "residential", "name", "outer", "type", "multipolygon"];
+
+        pbf::PrimitiveBlock {
+            stringtable: pbf::StringTable {
+                s: strings.iter().map(|s| s.as_bytes().to_vec()).collect(),
+            },
+            primitivegroup: vec![
+                pbf::PrimitiveGroup {
+                    nodes: vec![pbf::Node {
+                        id: 1,
+                        keys: vec![1],
+                        vals: vec![2],
+                        info: None,
+                        lat: 10,
+                        lon: 20,
+                    }],
+                    dense: Some(pbf::DenseNodes {
+                        id: vec![2, 1],
+                        denseinfo: None,
+                        lat: vec![30, 1],
+                        lon: vec![40, 1],
+                        keys_vals: vec![0, 1, 2, 0],
+                    }),
+                    ..Default::default()
+                },
+                pbf::PrimitiveGroup {
+                    ways: vec![pbf::Way {
+                        id: 10,
+                        keys: vec![1],
+                        vals: vec![2],
+                        refs: vec![1, 1, 1],
+                        ..Default::default()
+                    }],
+                    relations: vec![pbf::Relation {
+                        id: 20,
+                        keys: vec![5],
+                        vals: vec![6],
+                        roles_sid: vec![4, 0],
+                        memids: vec![10, -9],
+                        types: vec![1, 0],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            ],
+            granularity: Some(100),
+            lat_offset: Some(1000),
+            lon_offset: Some(2000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn iterate_elements() {
+        let block = test_block();
+        let elements: Vec<_> = block.elements().collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(elements.iter().map(Element::id).collect::<Vec<_>>(), [1, 2, 3, 10, 20]);
+        assert!(format!("{:?}", elements[3]).starts_with("Way(Way { way: Way { id: 10"));
+
+        let Element::Node(sparse_node) = &elements[0] else {
+            panic!("expected a node");
+        };
+        assert_eq!((sparse_node.lat().unwrap(), sparse_node.lon().unwrap()), (2000, 4000));
+        assert_eq!(sparse_node.location().unwrap(), LatLon::new(2000, 4000).unwrap());
+        assert!(matches!(
+            sparse_node.tags().next(),
+            Some((Ok("highway"), Ok("residential")))
+        ));
+
+        let Element::Node(dense_node) = &elements[2] else {
+            panic!("expected a node");
+        };
+        assert_eq!((dense_node.lat().unwrap(), dense_node.lon().unwrap()), (4100, 6100));
+        assert!(matches!(
+            dense_node.tags().next(),
+            Some((Ok("highway"), Ok("residential")))
+        ));
+        assert_eq!(elements[1].tags().count(), 0);
+
+        let Element::Way(way) = &elements[3] else {
+            panic!("expected a way");
+        };
+        assert_eq!(way.refs().collect::<Result<Vec<_>, _>>().unwrap(), [1, 2, 3]);
+
+        let Element::Relation(relation) = &elements[4] else {
+            panic!("expected a relation");
+        };
+        assert!(matches!(relation.tags().next(), Some((Ok("type"), Ok("multipolygon")))));
+
+        let members: Vec<_> = relation.members().unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            members,
+            [
+                Member {
+                    id: 10,
+                    member_type: pbf::relation::MemberType::Way,
+                    role: "outer"
+                },
+                Member {
+                    id: 1,
+                    member_type: pbf::relation::MemberType::Node,
+                    role: ""
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_dense_nodes() {
+        let mut block = test_block();
+        block.primitivegroup[0].dense.as_mut().unwrap().lat.pop();
+
+        let results: Vec<_> = block.elements().collect();
+
+        assert_eq!(results.len(), 4);
+        assert!(matches!(results[1], Err(Error::LogicError(_))));
+        assert_eq!(results[2].as_ref().unwrap().id(), 10);
+    }
+
+    #[test]
+    fn invalid_relation_members() {
+        let string_table = pbf::StringTable {
+            s: vec![b"".to_vec(), b"outer".to_vec()],
+        };
+
+        let relation = |roles_sid: Vec<i32>, types: Vec<i32>| pbf::Relation {
+            memids: vec![1, 1],
+            roles_sid,
+            types,
+            ..Default::default()
+        };
+
+        assert!(RelationMemberReader::new(&relation(vec![1], vec![1, 1]), &string_table).is_err());
+        assert!(RelationMemberReader::new(&relation(vec![1, 1], vec![1]), &string_table).is_err());
+
+        let invalid_type = relation(vec![1, 1], vec![1, 3]);
+        let results: Vec<_> = RelationMemberReader::new(&invalid_type, &string_table)
+            .unwrap()
+            .collect();
+        assert_eq!(results[0].as_ref().unwrap().role, "outer");
+        assert!(matches!(results[1], Err(Error::LogicError(_))));
+
+        for roles_sid in [vec![1, -1], vec![1, 2]] {
+            let invalid_role = relation(roles_sid, vec![1, 1]);
+            let results: Vec<_> = RelationMemberReader::new(&invalid_role, &string_table)
+                .unwrap()
+                .collect();
+            assert_eq!(results.len(), 2);
+            assert!(results[0].is_ok());
+            assert!(matches!(results[1], Err(Error::LogicError(_))));
+        }
+
+        let overflowing_id = pbf::Relation {
+            memids: vec![i64::MAX, 1],
+            ..relation(vec![1, 1], vec![1, 1])
+        };
+        let results: Vec<_> = RelationMemberReader::new(&overflowing_id, &string_table)
+            .unwrap()
+            .collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().id, i64::MAX);
+        assert!(matches!(results[1], Err(Error::LogicError(_))));
+    }
+
+    #[test]
+    fn read_sparse_nodes() {
+        let block = test_block();
+        let group = &block.primitivegroup[0];
+
+        let reader = NodeReader::new(&group.nodes, &block);
+        assert_eq!(reader.len(), 1);
+
+        let nodes: Vec<_> = reader.collect();
+        assert_eq!(nodes[0].id(), 1);
+        assert_eq!((nodes[0].lat().unwrap(), nodes[0].lon().unwrap()), (2000, 4000));
+        assert!(nodes[0].info().is_none());
+        assert!(matches!(
+            nodes[0].tags().next(),
+            Some((Ok("highway"), Ok("residential")))
+        ));
+
+        let dense_node = DenseNodeReader::new(group.dense.as_ref().unwrap())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let dense_node = Node::from_dense(dense_node, &block);
+        assert_eq!(
+            (dense_node.id(), dense_node.lat().unwrap(), dense_node.lon().unwrap()),
+            (2, 4000, 6000)
+        );
+    }
+
+    #[test]
+    fn decode_metadata() {
+        let mut block = test_block();
+        block.date_granularity = Some(500);
+
+        let info = pbf::Info {
+            version: Some(3),
+            timestamp: Some(-4),
+            changeset: Some(100),
+            uid: Some(7),
+            user_sid: Some(3),
+            visible: None,
+        };
+        block.primitivegroup[1].ways[0].info = Some(info);
+
+        let way = block.elements().nth(3).unwrap().unwrap();
+        let metadata = way.metadata().unwrap().unwrap();
+        assert_eq!(
+            metadata,
+            Metadata {
+                version: Some(3),
+                timestamp: Some(UNIX_EPOCH - Duration::from_secs(2)),
+                changeset: Some(100),
+                uid: Some(7),
+                user: Some("name"),
+                visible: None,
+            }
+        );
+        assert!(block.elements().next().unwrap().unwrap().metadata().is_none());
+
+        let invalid_user = pbf::Info {
+            user_sid: Some(7),
+            ..info
+        };
+        assert!(matches!(
+            Metadata::new(&invalid_user, &block),
+            Err(Error::LogicError(_))
+        ));
+
+        let invalid_timestamp = pbf::Info {
+            timestamp: Some(i64::MAX),
+            ..info
+        };
+        assert!(matches!(
+            Metadata::new(&invalid_timestamp, &block),
+            Err(Error::LogicError(_))
+        ));
+    }
+
+    #[test]
+    fn way_locations() {
+        let mut block = test_block();
+
+        let Some(Ok(Element::Way(way))) = block.elements().nth(3) else {
+            panic!("expected a way");
+        };
+        assert!(matches!(way.locations(), Err(Error::LogicError(_))));
+
+        let way = &mut block.primitivegroup[1].ways[0];
+        way.lat = vec![10, 1, -2];
+        way.lon = vec![20, -1, 2];
+
+        let Some(Ok(Element::Way(way))) = block.elements().nth(3) else {
+            panic!("expected a way");
+        };
+        assert_eq!(
+            way.locations().unwrap().collect::<Result<Vec<_>, _>>().unwrap(),
+            [
+                WayNode {
+                    id: 1,
+                    location: LatLon::new(2000, 4000).unwrap()
+                },
+                WayNode {
+                    id: 2,
+                    location: LatLon::new(2100, 3900).unwrap()
+                },
+                WayNode {
+                    id: 3,
+                    location: LatLon::new(1900, 4100).unwrap()
+                },
+            ]
+        );
+
+        // Overflowing coordinates are errors
+        block.primitivegroup[1].ways[0].lat[2] = i64::MAX / 2;
+
+        let Some(Ok(Element::Way(way))) = block.elements().nth(3) else {
+            panic!("expected a way");
+        };
+        let nodes: Vec<_> = way.locations().unwrap().collect();
+        assert!(nodes[..2].iter().all(Result::is_ok));
+        assert!(matches!(nodes[2], Err(Error::LogicError(_))));
+
+        // So are overflowing deltas
+        let way = &mut block.primitivegroup[1].ways[0];
+        way.lat = vec![i64::MAX, 1, 0];
+        way.refs = vec![1, i64::MAX, 0];
+
+        let Some(Ok(Element::Way(way))) = block.elements().nth(3) else {
+            panic!("expected a way");
+        };
+        assert!(matches!(
+            way.locations().unwrap().collect::<Result<Vec<_>, _>>(),
+            Err(Error::LogicError(_))
+        ));
+        assert!(matches!(
+            way.refs().collect::<Result<Vec<_>, _>>(),
+            Err(Error::LogicError(_))
+        ));
+    }
+}
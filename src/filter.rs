@@ -0,0 +1,330 @@
+//! Utilities for filtering primitive blocks.
+
+use crate::dense::{DenseNodeReader, DenseNodeWriter};
+use crate::element::{Element, Node, Relation, Way};
+use crate::util::new_string_table;
+use crate::{pbf, Error};
+
+/// Returns a copy of `block` with only the elements for which `predicate` returns `true`.
+///
+/// The primitive groups of `block` are kept (except for those which become empty), and the granularities and offsets
+/// of the block are unchanged. The string table is compacted to the strings referenced by the remaining elements, and
+/// dense nodes are delta encoded again. Changesets of primitive groups are dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::filter::filter_primitive_block;
+/// use rosm_pbf_reader::{pbf, Error};
+///
+/// fn highways(block: &pbf::PrimitiveBlock) -> Result<pbf::PrimitiveBlock, Error> {
+///     filter_primitive_block(block, |element| {
+///         element.tags().any(|(key, _)| matches!(key, Ok("highway")))
+///     })
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if the dense nodes of `block` are invalid, or an element references a string which is not in
+/// the string table of `block`.
+pub fn filter_primitive_block<Predicate>(
+    block: &pbf::PrimitiveBlock,
+    mut predicate: Predicate,
+) -> Result<pbf::PrimitiveBlock, Error>
+where
+    Predicate: FnMut(&Element<'_>) -> bool,
+{
+    let mut strings = StringTableCompactor::new(&block.stringtable);
+    let mut primitivegroup = Vec::new();
+
+    for group in &block.primitivegroup {
+        let mut filtered_group = pbf::PrimitiveGroup::default();
+
+        for node in &group.nodes {
+            if predicate(&Element::Node(Node::from_sparse(node, block))) {
+                filtered_group.nodes.push(pbf::Node {
+                    keys: strings.remap_all(&node.keys)?,
+                    vals: strings.remap_all(&node.vals)?,
+                    info: strings.remap_info(node.info)?,
+                    ..node.clone()
+                });
+            }
+        }
+
+        if let Some(dense_nodes) = &group.dense {
+            let mut writer = DenseNodeWriter::new();
+
+            for node in DenseNodeReader::new(dense_nodes)? {
+                let node = node?;
+
+                if predicate(&Element::Node(Node::from_dense(node.clone(), block))) {
+                    let key_value_indices = node
+                        .key_value_indices
+                        .iter()
+                        .map(|index| strings.remap_dense(*index))
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    let info = strings.remap_info(node.info)?;
+                    writer.push(node.id, node.lat, node.lon, &key_value_indices, info.as_ref())?;
+                }
+            }
+
+            if !writer.is_empty() {
+                filtered_group.dense = Some(writer.finish());
+            }
+        }
+
+        for way in &group.ways {
+            if predicate(&Element::Way(Way::new(way, block))) {
+                filtered_group.ways.push(pbf::Way {
+                    keys: strings.remap_all(&way.keys)?,
+                    vals: strings.remap_all(&way.vals)?,
+                    info: strings.remap_info(way.info)?,
+                    ..way.clone()
+                });
+            }
+        }
+
+        for relation in &group.relations {
+            if predicate(&Element::Relation(Relation::new(relation, block))) {
+                filtered_group.relations.push(pbf::Relation {
+                    keys: strings.remap_all(&relation.keys)?,
+                    vals: strings.remap_all(&relation.vals)?,
+                    info: strings.remap_info(relation.info)?,
+                    roles_sid: relation
+                        .roles_sid
+                        .iter()
+                        .map(|index| strings.remap_dense(*index))
+                        .collect::<Result<_, _>>()?,
+                    ..relation.clone()
+                });
+            }
+        }
+
+        if filtered_group.nodes.is_empty()
+            && filtered_group.dense.is_none()
+            && filtered_group.ways.is_empty()
+            && filtered_group.relations.is_empty()
+        {
+            continue;
+        }
+
+        primitivegroup.push(filtered_group);
+    }
+
+    Ok(pbf::PrimitiveBlock {
+        stringtable: strings.finish(),
+        primitivegroup,
+        ..*block
+    })
+}
+
+/// Builds a compacted string table, containing only the strings of the original table which are remapped.
+struct StringTableCompactor<'a> {
+    string_table: &'a pbf::StringTable,
+    new_indices: Vec<Option<u32>>, // New index of each string of the original string table
+    strings: Vec<Vec<u8>>,
+}
+
+impl<'a> StringTableCompactor<'a> {
+    fn new(string_table: &'a pbf::StringTable) -> Self {
+        let mut new_indices = vec![None; string_table.s.len()];
+        if let Some(first) = new_indices.first_mut() {
+            // Index 0 is reserved, see `new_string_table`
+            *first = Some(0);
+        }
+
+        StringTableCompactor {
+            string_table,
+            new_indices,
+            strings: new_string_table(),
+        }
+    }
+
+    fn remap(&mut self, index: u32) -> Result<u32, Error> {
+        let Some(new_index) = self.new_indices.get_mut(index as usize) else {
+            return Err(Error::LogicError(format!(
+                "string table index {index} is out of bounds ({})",
+                self.string_table.s.len()
+            )));
+        };
+
+        Ok(*new_index.get_or_insert_with(|| {
+            self.strings.push(self.string_table.s[index as usize].clone());
+            (self.strings.len() - 1) as u32
+        }))
+    }
+
+    fn remap_all(&mut self, indices: &[u32]) -> Result<Vec<u32>, Error> {
+        indices.iter().map(|index| self.remap(*index)).collect()
+    }
+
+    fn remap_dense(&mut self, index: i32) -> Result<i32, Error> {
+        let index =
+            u32::try_from(index).map_err(|_err| Error::LogicError(format!("string table index {index} is invalid")))?;
+        Ok(self.remap(index)? as i32)
+    }
+
+    fn remap_info(&mut self, info: Option<pbf::Info>) -> Result<Option<pbf::Info>, Error> {
+        match info {
+            Some(info) => Ok(Some(pbf::Info {
+                user_sid: info.user_sid.map(|user_sid| self.remap(user_sid)).transpose()?,
+                ..info
+            })),
+            None => Ok(None),
+        }
+    }
+
+    fn finish(self) -> pbf::StringTable {
+        pbf::StringTable { s: self.strings }
+    }
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use super::*;
+    use crate::builder::PrimitiveBlockBuilder;
+    use crate::element::{Member, Metadata, PrimitiveBlockExt};
+    use crate::util::LatLon;
+
+    fn has_highway_tag(element: &Element<'_>) -> bool {
+        element.tags().any(|(key, _)| matches!(key, Ok("highway")))
+    }
+
+    fn test_block() -> pbf::PrimitiveBlock {
+        let mut builder = PrimitiveBlockBuilder::new();
+        let location = |lat, lon| LatLon::new(lat, lon).unwrap();
+        let metadata = Metadata {
+            version: Some(1),
+            timestamp: None,
+            changeset: None,
+            uid: Some(7),
+            user: Some("mapper"),
+            visible: None,
+        };
+
+        builder
+            .add_node(1, location(100, 200), [("amenity", "bench")], None)
+            .unwrap();
+        builder
+            .add_node(2, location(300, 400), [("highway", "crossing")], Some(&metadata))
+            .unwrap();
+        builder.add_node(3, location(500, 600), [], None).unwrap();
+        builder
+            .add_node(4, location(700, 800), [("highway", "stop")], None)
+            .unwrap();
+        builder
+            .add_way(10, [1, 2, 3], [("building", "yes"), ("name", "Town hall")], None)
+            .unwrap();
+        builder
+            .add_way(11, [2, 4], [("highway", "residential")], Some(&metadata))
+            .unwrap();
+        builder
+            .add_relation(
+                20,
+                [Member {
+                    id: 10,
+                    member_type: pbf::relation::MemberType::Way,
+                    role: "outer",
+                }],
+                [("type", "multipolygon")],
+                None,
+            )
+            .unwrap();
+
+        let mut block = builder.build();
+
+        // Normal nodes are kept as normal nodes
+        block.primitivegroup.push(pbf::PrimitiveGroup {
+            nodes: vec![pbf::Node {
+                id: 5,
+                keys: vec![block.stringtable.s.iter().position(|s| s == b"highway").unwrap() as u32],
+                vals: vec![block.stringtable.s.iter().position(|s| s == b"stop").unwrap() as u32],
+                info: None,
+                lat: 9,
+                lon: 10,
+            }],
+            ..Default::default()
+        });
+
+        block
+    }
+
+    #[test]
+    fn filter_highways() {
+        let block = test_block();
+        let filtered_block = filter_primitive_block(&block, has_highway_tag).unwrap();
+
+        let elements: Vec<_> = filtered_block.elements().collect::<Result<_, _>>().unwrap();
+        assert_eq!(elements.iter().map(Element::id).collect::<Vec<_>>(), [2, 4, 11, 5]);
+
+        let mut strings: Vec<_> = filtered_block
+            .stringtable
+            .s
+            .iter()
+            .map(|s| std::str::from_utf8(s).unwrap())
+            .collect();
+        assert_eq!(strings.remove(0), "");
+        strings.sort_unstable();
+        assert_eq!(strings, ["crossing", "highway", "mapper", "residential", "stop"]);
+
+        let Element::Node(node) = &elements[1] else {
+            panic!("expected a node");
+        };
+        assert_eq!((node.lat().unwrap(), node.lon().unwrap()), (700, 800));
+        assert!(matches!(node.tags().next(), Some((Ok("highway"), Ok("stop")))));
+
+        let Element::Node(node) = &elements[0] else {
+            panic!("expected a node");
+        };
+        assert_eq!(node.metadata().unwrap().unwrap().user, Some("mapper"));
+
+        let Element::Way(way) = &elements[2] else {
+            panic!("expected a way");
+        };
+        assert_eq!(way.refs().collect::<Result<Vec<_>, _>>().unwrap(), [2, 4]);
+        assert_eq!(way.metadata().unwrap().unwrap().user, Some("mapper"));
+
+        let Element::Node(node) = &elements[3] else {
+            panic!("expected a node");
+        };
+        assert!(matches!(node.tags().next(), Some((Ok("highway"), Ok("stop")))));
+
+        assert_eq!(filtered_block.primitivegroup.len(), 3);
+        assert_eq!(filtered_block.granularity, block.granularity);
+    }
+
+    #[test]
+    fn keep_all_or_nothing() {
+        let block = test_block();
+
+        let filtered_block = filter_primitive_block(&block, |_| true).unwrap();
+        let ids = |block: &pbf::PrimitiveBlock| -> Vec<i64> { block.elements().map(|e| e.unwrap().id()).collect() };
+        assert_eq!(ids(&filtered_block), ids(&block));
+
+        let relation = &filtered_block.primitivegroup[2].relations[0];
+        let member = Relation::new(relation, &filtered_block)
+            .members()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(member.role, "outer");
+
+        let filtered_block = filter_primitive_block(&block, |_| false).unwrap();
+        assert!(filtered_block.primitivegroup.is_empty());
+        assert_eq!(filtered_block.stringtable.s, [b""]);
+    }
+
+    #[test]
+    fn invalid_string_index() {
+        let mut block = test_block();
+        block.primitivegroup[1].ways[0].keys[0] = 1000;
+
+        assert!(matches!(
+            filter_primitive_block(&block, |_| true),
+            Err(Error::LogicError(_))
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+//! Helpers for reading header blocks.
+
+use crate::pbf;
+use crate::util::{nanodegrees_to_degrees, system_time_from_millis};
+
+use std::time::SystemTime;
+
+/// A required feature of an OSM PBF file, listed in [`pbf::HeaderBlock::required_features`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature<'a> {
+    /// `OsmSchema-V0.6`: the file contains data with the OSM v0.6 schema.
+    OsmSchemaV06,
+    /// `DenseNodes`: the file contains dense nodes.
+    DenseNodes,
+    /// `HistoricalInformation`: the file contains historical versions of elements, with the visible flag set.
+    HistoricalInformation,
+    /// A feature not known by the library.
+    Unknown(&'a str),
+}
+
+impl<'a> Feature<'a> {
+    /// Classifies a required feature string.
+    pub fn new(feature: &'a str) -> Self {
+        match feature {
+            "OsmSchema-V0.6" => Feature::OsmSchemaV06,
+            "DenseNodes" => Feature::DenseNodes,
+            "HistoricalInformation" => Feature::HistoricalInformation,
+            _ => Feature::Unknown(feature),
+        }
+    }
+
+    /// Returns whether the feature is supported by the library, i.e. it's not [`Feature::Unknown`].
+    pub fn is_supported(&self) -> bool {
+        !matches!(self, Feature::Unknown(_))
+    }
+}
+
+/// Bounding box of the data in an OSM PBF file, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    /// Minimum longitude.
+    pub left: f64,
+    /// Maximum longitude.
+    pub right: f64,
+    /// Maximum latitude.
+    pub top: f64,
+    /// Minimum latitude.
+    pub bottom: f64,
+}
+
+impl From<&pbf::HeaderBBox> for BoundingBox {
+    fn from(bbox: &pbf::HeaderBBox) -> Self {
+        BoundingBox {
+            left: nanodegrees_to_degrees(bbox.left),
+            right: nanodegrees_to_degrees(bbox.right),
+            top: nanodegrees_to_degrees(bbox.top),
+            bottom: nanodegrees_to_degrees(bbox.bottom),
+        }
+    }
+}
+
+/// Borrowed view of a [`pbf::HeaderBlock`] with decoded fields.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::header::Header;
+/// use rosm_pbf_reader::pbf;
+///
+/// fn process_header_block(header_block: pbf::HeaderBlock) {
+///     let header = Header::new(&header_block);
+///
+///     if let Some(bbox) = header.bbox() {
+///         println!("bounding box: {bbox:?}");
+///     }
+///
+///     for feature in header.unsupported_features() {
+///         println!("unsupported required feature: {feature}");
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Header<'a> {
+    header_block: &'a pbf::HeaderBlock,
+}
+
+impl<'a> Header<'a> {
+    /// Constructs a new `Header` view of `header_block`.
+    pub fn new(header_block: &'a pbf::HeaderBlock) -> Self {
+        Header { header_block }
+    }
+
+    /// Returns the optional bounding box of the data.
+    pub fn bbox(&self) -> Option<BoundingBox> {
+        self.header_block.bbox.as_ref().map(BoundingBox::from)
+    }
+
+    /// Returns an iterator over the features required to read the file.
+    pub fn required_features(&self) -> impl Iterator<Item = Feature<'a>> + 'a {
+        self.header_block.required_features.iter().map(|f| Feature::new(f))
+    }
+
+    /// Returns an iterator over the required features which aren't supported by the library.
+    pub fn unsupported_features(&self) -> impl Iterator<Item = &'a str> + 'a {
+        self.required_features().filter_map(|feature| match feature {
+            Feature::Unknown(feature) => Some(feature),
+            _ => None,
+        })
+    }
+
+    /// Returns an iterator over the optional features of the file, like `Sort.Type_then_ID` or `LocationsOnWays`.
+    pub fn optional_features(&self) -> impl Iterator<Item = &'a str> + 'a {
+        self.header_block.optional_features.iter().map(String::as_str)
+    }
+
+    /// Returns the optional name of the program which has written the file.
+    pub fn writing_program(&self) -> Option<&'a str> {
+        self.header_block.writingprogram.as_deref()
+    }
+
+    /// Returns the optional source of the bounding box.
+    pub fn source(&self) -> Option<&'a str> {
+        self.header_block.source.as_deref()
+    }
+
+    /// Returns the optional timestamp of the Osmosis replication state of the file.
+    pub fn replication_timestamp(&self) -> Option<SystemTime> {
+        self.header_block
+            .osmosis_replication_timestamp
+            .and_then(|timestamp| timestamp.checked_mul(1000))
+            .and_then(system_time_from_millis)
+    }
+
+    /// Returns the optional sequence number of the Osmosis replication state of the file.
+    pub fn replication_sequence_number(&self) -> Option<i64> {
+        self.header_block.osmosis_replication_sequence_number
+    }
+
+    /// Returns the optional base URL of the Osmosis replication state of the file.
+    pub fn replication_base_url(&self) -> Option<&'a str> {
+        self.header_block.osmosis_replication_base_url.as_deref()
+    }
+
+    /// Returns the raw header block.
+    pub fn raw(&self) -> &'a pbf::HeaderBlock {
+        self.header_block
+    }
+}
+
+#[cfg(test)]
+mod header_tests {
+    use super::*;
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn decoded_fields() {
+        let header_block = pbf::HeaderBlock {
+            bbox: Some(pbf::HeaderBBox {
+                left: -1_500_000_000,
+                right: 2_000_000_000,
+                top: 45_250_000_000,
+                bottom: 44_000_000_000,
+            }),
+            required_features: vec![
+                "OsmSchema-V0.6".to_string(),
+                "DenseNodes".to_string(),
+                "Foo".to_string(),
+            ],
+            optional_features: vec!["LocationsOnWays".to_string()],
+            osmosis_replication_timestamp: Some(1_700_000_000),
+            ..Default::default()
+        };
+
+        let header = Header::new(&header_block);
+
+        assert_eq!(
+            header.bbox(),
+            Some(BoundingBox {
+                left: -1.5,
+                right: 2.0,
+                top: 45.25,
+                bottom: 44.0
+            })
+        );
+        assert_eq!(
+            header.required_features().collect::<Vec<_>>(),
+            [Feature::OsmSchemaV06, Feature::DenseNodes, Feature::Unknown("Foo")]
+        );
+        assert_eq!(header.unsupported_features().collect::<Vec<_>>(), ["Foo"]);
+        assert_eq!(header.optional_features().collect::<Vec<_>>(), ["LocationsOnWays"]);
+        assert_eq!(
+            header.replication_timestamp(),
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert!(header.writing_program().is_none());
+    }
+}
@@ -0,0 +1,220 @@
+//! Random access to the blobs of seekable inputs.
+
+use crate::{
+    read_blob_header_inner, read_blob_inner, read_header_size_buffer, BlobPosition, Error, RawBlock, ReaderLimits,
+};
+
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+/// Location and type of a single blob, stored in a [`BlobIndex`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobIndexEntry {
+    /// Byte offset of the blob (the start of its 4 byte header size) from the start of the input.
+    pub offset: u64,
+    /// Size of the encoded blob header in bytes.
+    pub header_size: usize,
+    /// Size of the (possibly compressed) blob data in bytes.
+    pub data_size: usize,
+    /// Type of the blob, like `OSMHeader` or `OSMData`.
+    pub r#type: String,
+}
+
+impl BlobIndexEntry {
+    /// Returns the byte offset of the blob data from the start of the input.
+    pub fn data_offset(&self) -> u64 {
+        self.offset + 4 + self.header_size as u64
+    }
+}
+
+/// Index of the blobs of a seekable input, allowing random access to them.
+///
+/// Building the index only reads blob headers, skipping blob data with seeking.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::index::BlobIndex;
+/// use rosm_pbf_reader::{Block, BlockParser};
+///
+/// use std::fs::File;
+///
+/// let mut file = File::open("some.osm.pbf").unwrap();
+/// let index = BlobIndex::build(&mut file).unwrap();
+///
+/// let mut block_parser = BlockParser::default();
+/// let raw_block = index.read_blob(&mut file, index.len() - 1).unwrap();
+///
+/// if let Block::Primitive(primitive_block) = block_parser.parse_block(raw_block).unwrap() {
+///     println!("last block has {} groups", primitive_block.primitivegroup.len());
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BlobIndex {
+    entries: Vec<BlobIndexEntry>,
+    limits: ReaderLimits,
+}
+
+impl BlobIndex {
+    /// Builds an index of all blobs in `pbf`, scanning it from its start.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if seeking or reading fails, an invalid blob header is encountered, or the last blob
+    /// is truncated.
+    pub fn build<Input>(pbf: &mut Input) -> Result<Self, Error>
+    where
+        Input: Read + Seek,
+    {
+        Self::build_with_limits(pbf, ReaderLimits::default())
+    }
+
+    /// Builds an index of all blobs in `pbf`, like [`BlobIndex::build`], but with custom size limits.
+    ///
+    /// The same limits are used when reading blobs with [`BlobIndex::read_blob`].
+    ///
+    /// # Errors
+    ///
+    /// See [`BlobIndex::build`].
+    pub fn build_with_limits<Input>(pbf: &mut Input, limits: ReaderLimits) -> Result<Self, Error>
+    where
+        Input: Read + Seek,
+    {
+        let input_size = pbf.seek(SeekFrom::End(0)).map_err(Error::IoError)?;
+        let mut offset = pbf.seek(SeekFrom::Start(0)).map_err(Error::IoError)?;
+
+        let mut entries = Vec::new();
+
+        while let Some(header_size_buffer) = read_header_size_buffer(pbf) {
+            let parsed_header = read_blob_header_inner(pbf, header_size_buffer?, &limits)?;
+
+            let entry = BlobIndexEntry {
+                offset,
+                header_size: parsed_header.header_size,
+                data_size: parsed_header.data_size,
+                r#type: parsed_header.header.r#type,
+            };
+
+            offset = entry.data_offset() + entry.data_size as u64;
+
+            if offset > input_size {
+                return Err(Error::IoError(ErrorKind::UnexpectedEof.into()));
+            }
+
+            pbf.seek(SeekFrom::Start(offset)).map_err(Error::IoError)?;
+            entries.push(entry);
+        }
+
+        if offset != input_size {
+            // Some, but not all bytes of the next header size could be read
+            return Err(Error::IoError(ErrorKind::UnexpectedEof.into()));
+        }
+
+        Ok(Self { entries, limits })
+    }
+
+    /// Returns the indexed blobs in input order.
+    pub fn entries(&self) -> &[BlobIndexEntry] {
+        &self.entries
+    }
+
+    /// Returns the number of indexed blobs.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the input contained no blobs.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the sequence index of the blob starting at byte `offset`, if there's any.
+    pub fn find_offset(&self, offset: u64) -> Option<usize> {
+        self.entries.binary_search_by_key(&offset, |entry| entry.offset).ok()
+    }
+
+    /// Reads the blob with the given sequence `index` from `pbf`, which must be the same input the index was
+    /// built from.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `index` is out of bounds, or seeking or reading fails.
+    pub fn read_blob<Input>(&self, pbf: &mut Input, index: usize) -> Result<RawBlock<'static>, Error>
+    where
+        Input: Read + Seek,
+    {
+        let Some(entry) = self.entries.get(index) else {
+            return Err(Error::LogicError(format!(
+                "blob index {index} is out of bounds ({})",
+                self.entries.len()
+            )));
+        };
+
+        pbf.seek(SeekFrom::Start(entry.offset)).map_err(Error::IoError)?;
+
+        let mut header_size_buffer = [0u8; 4];
+        pbf.read_exact(&mut header_size_buffer).map_err(Error::IoError)?;
+
+        let mut raw_block = read_blob_inner(pbf, header_size_buffer, &self.limits)?;
+        raw_block.position = Some(BlobPosition {
+            index,
+            offset: entry.offset,
+        });
+
+        Ok(raw_block)
+    }
+}
+
+#[cfg(test)]
+mod blob_index_tests {
+    use super::*;
+    use crate::test_utils::write_blob;
+
+    use std::io::Cursor;
+
+    #[test]
+    fn random_access() {
+        let mut pbf = Vec::new();
+        write_blob(&mut pbf, "OSMHeader", &[1, 2, 3]);
+        let second_offset = pbf.len() as u64;
+        write_blob(&mut pbf, "OSMData", &[4, 5]);
+        write_blob(&mut pbf, "Custom", &[6]);
+
+        let mut pbf = Cursor::new(pbf);
+        let index = BlobIndex::build(&mut pbf).expect("index should be built on valid data");
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.entries()[1].offset, second_offset);
+        assert_eq!(index.entries()[1].data_size, 2);
+        assert_eq!(index.entries()[2].r#type, "Custom");
+        assert_eq!(index.find_offset(second_offset), Some(1));
+        assert_eq!(index.find_offset(second_offset + 1), None);
+
+        let raw_block = index.read_blob(&mut pbf, 1).unwrap();
+        assert_eq!(raw_block.r#type(), "OSMData");
+        assert_eq!(raw_block.data(), [4, 5]);
+        assert_eq!(
+            raw_block.position(),
+            Some(BlobPosition {
+                index: 1,
+                offset: second_offset
+            })
+        );
+
+        let raw_block = index.read_blob(&mut pbf, 0).unwrap();
+        assert_eq!(raw_block.data(), [1, 2, 3]);
+
+        assert!(index.read_blob(&mut pbf, 3).is_err());
+    }
+
+    #[test]
+    fn truncated_input() {
+        let mut pbf = Vec::new();
+        write_blob(&mut pbf, "OSMData", &[1, 2, 3]);
+        pbf.pop();
+
+        assert!(matches!(
+            BlobIndex::build(&mut Cursor::new(pbf)),
+            Err(Error::IoError(_))
+        ));
+    }
+}
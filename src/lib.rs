@@ -1,12 +1,14 @@
 //! A low-level library for parsing OSM data in PBF format.
 //!
-//! An OSM PBF file is a sequence of blobs. These blobs can be read with [`read_blob`]. The
-//! [`RawBlock`]s returned by `read_blob` can then be decompressed and parsed by
-//! [`BlockParser::parse_block`], which returns a [`Block`], containing either a parsed
+//! An OSM PBF file is a sequence of blobs. These blobs can be read with [`read_blob`], or iterated with a
+//! [`BlobReader`] (or a [`BlobSliceReader`] for in-memory inputs). The [`RawBlock`]s returned by them can then be
+//! decompressed and parsed by [`BlockParser::parse_block`], which returns a [`Block`], containing either a parsed
 //! header/primitive block or an unknown block's binary data.
 //!
 //! The library also provides utilities for reading densely or delta encoded data in these blocks.
 //!
+//! Header and primitive blocks can also be written into blobs with [`writer::PbfWriter`].
+//!
 //! Raw header and primitive block definitions (generated by [Prost](https://github.com/tokio-rs/prost)) are exported
 //! through the `pbf` module.
 //!
@@ -16,20 +18,33 @@
 
 #![forbid(unsafe_code)]
 
-#[cfg(feature = "default")]
+#[cfg(feature = "flate2")]
 use flate2::read::ZlibDecoder;
 
 use prost::Message;
 
+use std::borrow::Cow;
 use std::convert::From;
-#[cfg(feature = "default")]
+#[cfg(any(feature = "flate2", feature = "zstd"))]
 use std::io::prelude::*;
 use std::io::ErrorKind;
 use std::str;
 
+pub mod builder;
 pub mod dense;
+pub mod element;
+pub mod filter;
+pub mod header;
+pub mod index;
+pub mod parallel;
 pub mod pbf;
+#[cfg(feature = "tokio")]
+pub mod tokio;
 pub mod util;
+pub mod writer;
+
+#[cfg(test)]
+mod test_utils;
 
 /// Possible errors returned by the library.
 #[derive(Debug)]
@@ -38,12 +53,25 @@ pub enum Error {
     PbfParseError(prost::DecodeError),
     /// Returned when reading from the input stream or decompression of blob data has failed.
     IoError(std::io::Error),
-    /// Returned when a blob header with an invalid size (negative or >=64 KB) is encountered.
+    /// Returned when a blob header with a negative size is encountered.
     InvalidBlobHeader,
-    /// Returned when blob data with an invalid size (negative or >=32 MB) is encountered.
+    /// Returned when blob data with a negative size or invalid content is encountered.
     InvalidBlobData,
+    /// Returned when the size of a blob header exceeds [`ReaderLimits::header_size_limit`].
+    BlobHeaderTooLarge { size: usize, limit: usize },
+    /// Returned when the size of blob data exceeds [`ReaderLimits::blob_size_limit`].
+    BlobDataTooLarge { size: usize, limit: usize },
+    /// Returned when the uncompressed size of blob data exceeds [`ReaderLimits::uncompressed_size_limit`].
+    UncompressedBlobTooLarge { size: usize, limit: usize },
+    /// Returned when blob data doesn't decompress to the uncompressed size declared in the blob. `actual` is `None`
+    /// if the data decompresses to more bytes than expected, since decompression is stopped early in that case.
+    RawSizeMismatch { expected: usize, actual: Option<usize> },
     /// Returned when an error has occured during blob decompression.
     DecompressionError(DecompressionError),
+    /// Returned when an error has occured during blob compression.
+    CompressionError(writer::CompressionError),
+    /// Returned by a strict [`BlockParser`] when a header block requires a feature not supported by the library.
+    UnsupportedFeature(String),
     /// Returned when some assumption in the data is violated (for example, an out of bounds index is encountered).
     LogicError(String),
 }
@@ -83,9 +111,93 @@ impl From<&str> for BlockType {
 }
 
 /// An unparsed, possibly compressed block.
-pub struct RawBlock {
-    r#type: BlockType,
-    data: Vec<u8>,
+///
+/// The blob data is either owned (`RawBlock<'static>`), or borrowed from an in-memory input by
+/// [`BlobSliceReader`].
+pub struct RawBlock<'a> {
+    header: pbf::BlobHeader,
+    header_size: usize,
+    data: Cow<'a, [u8]>,
+    position: Option<BlobPosition>,
+}
+
+impl RawBlock<'_> {
+    /// Returns the type of the block, like `OSMHeader` or `OSMData`.
+    ///
+    /// Blocks of unknown types are parsed into [`Block::Unknown`], so this can be used to dispatch them.
+    pub fn r#type(&self) -> &str {
+        &self.header.r#type
+    }
+
+    /// Returns the optional, producer specific index data of the block's blob header.
+    pub fn index_data(&self) -> Option<&[u8]> {
+        self.header.indexdata.as_deref()
+    }
+
+    /// Returns the size of the encoded blob header in bytes, not including its 4 byte size prefix.
+    pub fn header_size(&self) -> usize {
+        self.header_size
+    }
+
+    /// Returns the size of the (possibly compressed) blob data in bytes.
+    pub fn data_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns the (possibly compressed) blob data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the position of the blob in its input stream, if known.
+    ///
+    /// Blocks read with [`BlobReader`] always have a known position, while those read with [`read_blob`] don't.
+    pub fn position(&self) -> Option<BlobPosition> {
+        self.position
+    }
+
+    /// Converts the block into one which owns its data, copying the data if it's borrowed.
+    pub fn into_owned(self) -> RawBlock<'static> {
+        RawBlock {
+            header: self.header,
+            header_size: self.header_size,
+            data: Cow::Owned(self.data.into_owned()),
+            position: self.position,
+        }
+    }
+}
+
+/// Position of a blob in its input stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobPosition {
+    /// Zero-based sequence index of the blob.
+    pub index: usize,
+    /// Byte offset of the blob (the start of its 4 byte header size) from the start of the input stream.
+    pub offset: u64,
+}
+
+/// Size limits applied when reading and parsing blobs.
+///
+/// The default limits are the hard limits of the OSM PBF format. Stricter limits can be used for untrusted input,
+/// while more relaxed ones allow reading non-conforming files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReaderLimits {
+    /// Blob headers must be smaller than this size in bytes. Defaults to 64 KiB.
+    pub header_size_limit: usize,
+    /// Blob data must be smaller than this size in bytes. Defaults to 32 MiB.
+    pub blob_size_limit: usize,
+    /// Decompressed blob data must be smaller than this size in bytes. Defaults to 32 MiB.
+    pub uncompressed_size_limit: usize,
+}
+
+impl Default for ReaderLimits {
+    fn default() -> Self {
+        Self {
+            header_size_limit: 64 * 1024,
+            blob_size_limit: 32 * 1024 * 1024,
+            uncompressed_size_limit: 32 * 1024 * 1024,
+        }
+    }
 }
 
 /// Reads the next blob from `pbf`.
@@ -106,7 +218,27 @@ pub struct RawBlock {
 ///     }
 /// }
 /// ```
-pub fn read_blob<Input>(pbf: &mut Input) -> Option<Result<RawBlock, Error>>
+pub fn read_blob<Input>(pbf: &mut Input) -> Option<Result<RawBlock<'static>, Error>>
+where
+    Input: std::io::Read,
+{
+    read_blob_with_limits(pbf, &ReaderLimits::default())
+}
+
+/// Reads the next blob from `pbf`, like [`read_blob`], but with custom size limits.
+pub fn read_blob_with_limits<Input>(pbf: &mut Input, limits: &ReaderLimits) -> Option<Result<RawBlock<'static>, Error>>
+where
+    Input: std::io::Read,
+{
+    match read_header_size_buffer(pbf) {
+        Some(Ok(header_size_buffer)) => Some(read_blob_inner(pbf, header_size_buffer, limits)),
+        Some(Err(error)) => Some(Err(error)),
+        None => None,
+    }
+}
+
+/// Reads the 4 byte header size preceding each blob. Returns `None` if the end of `pbf` is reached.
+fn read_header_size_buffer<Input>(pbf: &mut Input) -> Option<Result<[u8; 4], Error>>
 where
     Input: std::io::Read,
 {
@@ -119,55 +251,682 @@ where
         };
     }
 
-    Some(read_blob_inner(pbf, header_size_buffer))
+    Some(Ok(header_size_buffer))
+}
+
+/// A decoded and validated blob header.
+struct ParsedBlobHeader {
+    header: pbf::BlobHeader,
+    header_size: usize,
+    data_size: usize,
 }
 
-fn read_blob_inner<Input>(pbf: &mut Input, header_size_buffer: [u8; 4]) -> Result<RawBlock, Error>
+fn read_blob_header_inner<Input>(
+    pbf: &mut Input,
+    header_size_buffer: [u8; 4],
+    limits: &ReaderLimits,
+) -> Result<ParsedBlobHeader, Error>
 where
     Input: std::io::Read,
 {
-    use pbf::BlobHeader;
+    let header_size = parse_header_size(header_size_buffer, limits)?;
 
-    let blob_header_size: usize = i32::from_be_bytes(header_size_buffer)
+    let mut header_buffer = vec![0u8; header_size];
+    if let Err(error) = pbf.read_exact(&mut header_buffer) {
+        return Err(Error::IoError(error));
+    }
+
+    parse_blob_header(&header_buffer, limits)
+}
+
+/// Decodes and validates the 4 byte header size preceding each blob.
+fn parse_header_size(header_size_buffer: [u8; 4], limits: &ReaderLimits) -> Result<usize, Error> {
+    let header_size: usize = i32::from_be_bytes(header_size_buffer)
         .try_into()
         .map_err(|_err| Error::InvalidBlobHeader)?;
 
-    if blob_header_size >= 64 * 1024 {
-        return Err(Error::InvalidBlobHeader);
+    if header_size >= limits.header_size_limit {
+        return Err(Error::BlobHeaderTooLarge {
+            size: header_size,
+            limit: limits.header_size_limit,
+        });
     }
 
-    let mut blob = vec![0u8; blob_header_size];
-    if let Err(error) = pbf.read_exact(&mut blob) {
-        return Err(Error::IoError(error));
-    }
+    Ok(header_size)
+}
 
-    let blob_header = match BlobHeader::decode(&*blob) {
+/// Decodes and validates an encoded blob header.
+fn parse_blob_header(header_buffer: &[u8], limits: &ReaderLimits) -> Result<ParsedBlobHeader, Error> {
+    let header = match pbf::BlobHeader::decode(header_buffer) {
         Ok(blob_header) => blob_header,
         Err(error) => return Err(Error::PbfParseError(error)),
     };
 
-    let block_type = BlockType::from(blob_header.r#type.as_ref());
-    let blob_size: usize = blob_header.datasize.try_into().map_err(|_err| Error::InvalidBlobData)?;
+    let data_size: usize = header.datasize.try_into().map_err(|_err| Error::InvalidBlobData)?;
 
-    if blob_size >= 32 * 1024 * 1024 {
-        return Err(Error::InvalidBlobData);
+    if data_size >= limits.blob_size_limit {
+        return Err(Error::BlobDataTooLarge {
+            size: data_size,
+            limit: limits.blob_size_limit,
+        });
     }
 
-    blob.resize_with(blob_size, Default::default);
+    Ok(ParsedBlobHeader {
+        header,
+        header_size: header_buffer.len(),
+        data_size,
+    })
+}
 
-    if let Err(error) = pbf.read_exact(&mut blob) {
-        return Err(Error::IoError(error));
+impl ParsedBlobHeader {
+    fn into_raw_block(self, data: Vec<u8>) -> RawBlock<'static> {
+        RawBlock {
+            header: self.header,
+            header_size: self.header_size,
+            data: Cow::Owned(data),
+            position: None,
+        }
     }
+}
 
-    let raw_block = RawBlock {
-        r#type: block_type,
-        data: blob,
-    };
+fn read_blob_inner<Input>(
+    pbf: &mut Input,
+    header_size_buffer: [u8; 4],
+    limits: &ReaderLimits,
+) -> Result<RawBlock<'static>, Error>
+where
+    Input: std::io::Read,
+{
+    let parsed_header = read_blob_header_inner(pbf, header_size_buffer, limits)?;
+    PendingBlob { parsed_header }.read_data(pbf)
+}
+
+/// Reads only the header of the next blob from `pbf`, leaving its data unread.
+///
+/// The returned [`PendingBlob`] can be used to decide whether to read the blob data or skip it. The data must be
+/// either read or skipped before reading the next blob from `pbf`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::read_blob_header;
+///
+/// use std::fs::File;
+///
+/// let mut file = File::open("some.osm.pbf").unwrap();
+///
+/// while let Some(result) = read_blob_header(&mut file) {
+///     let pending_blob = result.unwrap();
+///
+///     if pending_blob.r#type() == "OSMHeader" {
+///         let raw_block = pending_blob.read_data(&mut file).unwrap();
+///     } else {
+///         pending_blob.skip_seek(&mut file).unwrap();
+///     }
+/// }
+/// ```
+pub fn read_blob_header<Input>(pbf: &mut Input) -> Option<Result<PendingBlob, Error>>
+where
+    Input: std::io::Read,
+{
+    read_blob_header_with_limits(pbf, &ReaderLimits::default())
+}
+
+/// Reads only the header of the next blob from `pbf`, like [`read_blob_header`], but with custom size limits.
+pub fn read_blob_header_with_limits<Input>(pbf: &mut Input, limits: &ReaderLimits) -> Option<Result<PendingBlob, Error>>
+where
+    Input: std::io::Read,
+{
+    match read_header_size_buffer(pbf) {
+        Some(Ok(header_size_buffer)) => Some(
+            read_blob_header_inner(pbf, header_size_buffer, limits).map(|parsed_header| PendingBlob { parsed_header }),
+        ),
+        Some(Err(error)) => Some(Err(error)),
+        None => None,
+    }
+}
+
+/// A blob whose header has been read by [`read_blob_header`], but whose data hasn't been read yet.
+pub struct PendingBlob {
+    parsed_header: ParsedBlobHeader,
+}
+
+impl PendingBlob {
+    /// Returns the type of the blob, like `OSMHeader` or `OSMData`.
+    pub fn r#type(&self) -> &str {
+        &self.parsed_header.header.r#type
+    }
+
+    /// Returns the optional, producer specific index data of the blob header.
+    pub fn index_data(&self) -> Option<&[u8]> {
+        self.parsed_header.header.indexdata.as_deref()
+    }
+
+    /// Returns the size of the encoded blob header in bytes, not including its 4 byte size prefix.
+    pub fn header_size(&self) -> usize {
+        self.parsed_header.header_size
+    }
+
+    /// Returns the size of the (possibly compressed) blob data in bytes.
+    pub fn data_size(&self) -> usize {
+        self.parsed_header.data_size
+    }
+
+    /// Reads the blob data from `pbf`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if reading from `pbf` fails.
+    pub fn read_data<Input>(self, pbf: &mut Input) -> Result<RawBlock<'static>, Error>
+    where
+        Input: std::io::Read,
+    {
+        let mut blob = vec![0u8; self.parsed_header.data_size];
+        if let Err(error) = pbf.read_exact(&mut blob) {
+            return Err(Error::IoError(error));
+        }
+
+        Ok(self.parsed_header.into_raw_block(blob))
+    }
+
+    /// Skips the blob data by reading and discarding it. Use [`PendingBlob::skip_seek`] for seekable inputs.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if reading from `pbf` fails or it ends before the end of the blob data.
+    pub fn skip<Input>(self, pbf: &mut Input) -> Result<(), Error>
+    where
+        Input: std::io::Read,
+    {
+        let data_size = self.parsed_header.data_size as u64;
+        let mut data = std::io::Read::take(pbf, data_size);
+
+        match std::io::copy(&mut data, &mut std::io::sink()) {
+            Ok(skipped_size) if skipped_size == data_size => Ok(()),
+            Ok(_) => Err(Error::IoError(ErrorKind::UnexpectedEof.into())),
+            Err(error) => Err(Error::IoError(error)),
+        }
+    }
+
+    /// Skips the blob data by seeking past it.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if seeking in `pbf` fails.
+    pub fn skip_seek<Input>(self, pbf: &mut Input) -> Result<(), Error>
+    where
+        Input: std::io::Seek,
+    {
+        let data_size = self.parsed_header.data_size as i64;
+
+        match pbf.seek(std::io::SeekFrom::Current(data_size)) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(Error::IoError(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod pending_blob_tests {
+    use super::*;
+    use crate::test_utils::write_blob;
+
+    use std::io::Cursor;
+
+    fn test_pbf() -> Vec<u8> {
+        let mut pbf = Vec::new();
+        write_blob(&mut pbf, "OSMHeader", &[1, 2, 3]);
+        write_blob(&mut pbf, "OSMData", &[4, 5]);
+        write_blob(&mut pbf, "OSMData", &[6]);
+        pbf
+    }
+
+    #[test]
+    fn skip_by_reading() {
+        let pbf = test_pbf();
+        let mut pbf = pbf.as_slice();
+
+        let header = read_blob_header(&mut pbf).unwrap().unwrap();
+        assert_eq!(header.r#type(), "OSMHeader");
+        assert_eq!(header.data_size(), 3);
+        header.skip(&mut pbf).unwrap();
+
+        let data = read_blob_header(&mut pbf).unwrap().unwrap();
+        assert_eq!(data.r#type(), "OSMData");
+        assert_eq!(data.read_data(&mut pbf).unwrap().data(), [4, 5]);
+
+        let data = read_blob_header(&mut pbf).unwrap().unwrap();
+        assert_eq!(data.data_size(), 1);
+        data.skip(&mut pbf).unwrap();
+
+        assert!(read_blob_header(&mut pbf).is_none());
+    }
+
+    #[test]
+    fn skip_by_seeking() {
+        let mut pbf = Cursor::new(test_pbf());
+
+        let header = read_blob_header(&mut pbf).unwrap().unwrap();
+        header.skip_seek(&mut pbf).unwrap();
+
+        let data = read_blob_header(&mut pbf).unwrap().unwrap();
+        data.skip_seek(&mut pbf).unwrap();
+
+        let data = read_blob_header(&mut pbf).unwrap().unwrap();
+        assert_eq!(data.read_data(&mut pbf).unwrap().data(), [6]);
+
+        assert!(read_blob_header(&mut pbf).is_none());
+    }
+
+    #[test]
+    fn skip_truncated_data() {
+        let mut pbf = test_pbf();
+        pbf.truncate(pbf.len() - 1);
+        let mut pbf = pbf.as_slice();
+
+        for _ in 0..2 {
+            read_blob_header(&mut pbf).unwrap().unwrap().skip(&mut pbf).unwrap();
+        }
+
+        let data = read_blob_header(&mut pbf).unwrap().unwrap();
+        assert!(matches!(data.skip(&mut pbf), Err(Error::IoError(_))));
+    }
+}
+
+/// Iterator over the blobs of an input stream, tracking the position of each blob.
+///
+/// Unlike calling [`read_blob`] in a loop, a `BlobReader` stops after the first error, since the input stream
+/// can't be resynchronized after a partial or invalid blob.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::BlobReader;
+///
+/// use std::fs::File;
+///
+/// let file = File::open("some.osm.pbf").unwrap();
+///
+/// for result in BlobReader::new(file) {
+///     match result {
+///         Ok(raw_block) => {
+///             let position = raw_block.position().unwrap();
+///             println!("blob #{} at offset {}", position.index, position.offset);
+///         }
+///         Err(error) => {}
+///     }
+/// }
+/// ```
+pub struct BlobReader<Input> {
+    pbf: Input,
+    limits: ReaderLimits,
+    next_position: BlobPosition,
+    finished: bool,
+}
+
+impl<Input> BlobReader<Input>
+where
+    Input: std::io::Read,
+{
+    /// Creates a new `BlobReader` reading from the start of `pbf`.
+    pub fn new(pbf: Input) -> Self {
+        Self::with_limits(pbf, ReaderLimits::default())
+    }
+
+    /// Creates a new `BlobReader` reading from the start of `pbf`, with custom size limits.
+    pub fn with_limits(pbf: Input, limits: ReaderLimits) -> Self {
+        Self {
+            pbf,
+            limits,
+            next_position: BlobPosition { index: 0, offset: 0 },
+            finished: false,
+        }
+    }
+
+    /// Returns the position of the next blob to be read.
+    pub fn next_position(&self) -> BlobPosition {
+        self.next_position
+    }
+
+    /// Consumes the `BlobReader`, returning the underlying input stream.
+    pub fn into_inner(self) -> Input {
+        self.pbf
+    }
+}
+
+impl<Input> Iterator for BlobReader<Input>
+where
+    Input: std::io::Read,
+{
+    type Item = Result<RawBlock<'static>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let mut counting_reader = CountingReader {
+            inner: &mut self.pbf,
+            count: 0,
+        };
+
+        let result = read_blob_with_limits(&mut counting_reader, &self.limits);
+        let byte_count = counting_reader.count;
+
+        match result {
+            Some(Ok(mut raw_block)) => {
+                raw_block.position = Some(self.next_position);
+                self.next_position.index += 1;
+                self.next_position.offset += byte_count;
+                Some(Ok(raw_block))
+            }
+            Some(Err(error)) => {
+                self.finished = true;
+                Some(Err(error))
+            }
+            None => {
+                self.finished = true;
+                if byte_count > 0 {
+                    Some(Err(Error::IoError(ErrorKind::UnexpectedEof.into())))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl<Input> std::iter::FusedIterator for BlobReader<Input> where Input: std::io::Read {}
+
+/// Iterator over the blobs of an in-memory input, like a memory-mapped file, returning blocks which borrow their data
+/// from the input instead of copying it.
+///
+/// Like [`BlobReader`], it tracks the position of each blob, and stops after the first error.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::{BlobSliceReader, BlockParser};
+///
+/// // A memory-mapped file can be used the same way, by dereferencing the map to a slice
+/// let pbf = std::fs::read("some.osm.pbf").unwrap();
+///
+/// let mut block_parser = BlockParser::default();
+///
+/// for result in BlobSliceReader::new(&pbf) {
+///     let block = block_parser.parse_block(result.unwrap()).unwrap();
+/// }
+/// ```
+pub struct BlobSliceReader<'a> {
+    pbf: &'a [u8],
+    limits: ReaderLimits,
+    next_position: BlobPosition,
+    finished: bool,
+}
+
+impl<'a> BlobSliceReader<'a> {
+    /// Creates a new `BlobSliceReader` reading from the start of `pbf`.
+    pub fn new(pbf: &'a [u8]) -> Self {
+        Self::with_limits(pbf, ReaderLimits::default())
+    }
+
+    /// Creates a new `BlobSliceReader` reading from the start of `pbf`, with custom size limits.
+    pub fn with_limits(pbf: &'a [u8], limits: ReaderLimits) -> Self {
+        Self {
+            pbf,
+            limits,
+            next_position: BlobPosition { index: 0, offset: 0 },
+            finished: false,
+        }
+    }
+
+    /// Returns the position of the next blob to be read.
+    pub fn next_position(&self) -> BlobPosition {
+        self.next_position
+    }
+
+    /// Returns the unread part of the input.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.pbf
+    }
+
+    fn read_next(&mut self) -> Result<RawBlock<'a>, Error> {
+        let unexpected_eof = || Error::IoError(ErrorKind::UnexpectedEof.into());
+
+        let (header_size_buffer, remaining) = split_slice(self.pbf, 4).ok_or_else(unexpected_eof)?;
+        let header_size_buffer = header_size_buffer.try_into().map_err(|_err| unexpected_eof())?;
+        let header_size = parse_header_size(header_size_buffer, &self.limits)?;
+
+        let (header_buffer, remaining) = split_slice(remaining, header_size).ok_or_else(unexpected_eof)?;
+        let parsed_header = parse_blob_header(header_buffer, &self.limits)?;
+
+        let (data, remaining) = split_slice(remaining, parsed_header.data_size).ok_or_else(unexpected_eof)?;
+
+        let raw_block = RawBlock {
+            header: parsed_header.header,
+            header_size,
+            data: Cow::Borrowed(data),
+            position: Some(self.next_position),
+        };
+
+        self.next_position.index += 1;
+        self.next_position.offset += (self.pbf.len() - remaining.len()) as u64;
+        self.pbf = remaining;
+
+        Ok(raw_block)
+    }
+}
+
+impl<'a> Iterator for BlobSliceReader<'a> {
+    type Item = Result<RawBlock<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished || self.pbf.is_empty() {
+            self.finished = true;
+            return None;
+        }
+
+        let result = self.read_next();
+        self.finished = result.is_err();
+        Some(result)
+    }
+}
+
+impl std::iter::FusedIterator for BlobSliceReader<'_> {}
+
+/// Splits `slice` at `mid`, or returns `None` if it's shorter than `mid`.
+fn split_slice(slice: &[u8], mid: usize) -> Option<(&[u8], &[u8])> {
+    if mid <= slice.len() {
+        Some(slice.split_at(mid))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod blob_slice_reader_tests {
+    use super::*;
+    use crate::test_utils::write_blob;
+
+    #[test]
+    fn borrowed_blocks() {
+        let mut pbf = Vec::new();
+        write_blob(&mut pbf, "OSMHeader", &[1, 2, 3]);
+        let second_offset = pbf.len() as u64;
+        write_blob(&mut pbf, "OSMData", &[4, 5]);
+
+        let mut reader = BlobSliceReader::new(&pbf);
+
+        let first = reader.next().unwrap().unwrap();
+        assert!(matches!(first.data, Cow::Borrowed(_)));
+        assert_eq!(first.r#type(), "OSMHeader");
+        assert_eq!(first.data(), [1, 2, 3]);
+        assert_eq!(first.position(), Some(BlobPosition { index: 0, offset: 0 }));
 
-    Ok(raw_block)
+        let second = reader.next().unwrap().unwrap();
+        assert_eq!(second.data(), [4, 5]);
+        assert_eq!(
+            second.position(),
+            Some(BlobPosition {
+                index: 1,
+                offset: second_offset
+            })
+        );
+
+        let second = second.into_owned();
+        assert!(matches!(second.data, Cow::Owned(_)));
+        assert_eq!(second.data(), [4, 5]);
+
+        assert!(reader.next().is_none());
+        assert!(reader.remaining().is_empty());
+    }
+
+    #[test]
+    fn stops_after_error() {
+        let mut pbf = Vec::new();
+        write_blob(&mut pbf, "OSMData", &[1, 2, 3]);
+        write_blob(&mut pbf, "OSMData", &[4, 5, 6]);
+        pbf.pop();
+
+        let mut reader = BlobSliceReader::new(&pbf);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(reader.next(), Some(Err(Error::IoError(_)))));
+        assert!(reader.next().is_none());
+    }
+}
+
+struct CountingReader<'a, Input> {
+    inner: &'a mut Input,
+    count: u64,
+}
+
+impl<Input> std::io::Read for CountingReader<'_, Input>
+where
+    Input: std::io::Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.count += size as u64;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod blob_reader_tests {
+    use super::*;
+    use crate::test_utils::write_blob;
+
+    #[test]
+    fn positions() {
+        let mut pbf = Vec::new();
+        write_blob(&mut pbf, "OSMHeader", &[1, 2, 3]);
+        let second_offset = pbf.len() as u64;
+        write_blob(&mut pbf, "OSMData", &[4, 5]);
+
+        let mut reader = BlobReader::new(pbf.as_slice());
+
+        let first = reader.next().unwrap().unwrap();
+        assert_eq!(first.position(), Some(BlobPosition { index: 0, offset: 0 }));
+        assert_eq!(first.r#type(), "OSMHeader");
+        assert_eq!(first.data(), [1, 2, 3]);
+        assert_eq!(first.data_size(), 3);
+        assert_eq!(second_offset, 4 + first.header_size() as u64 + 3);
+
+        let second = reader.next().unwrap().unwrap();
+        assert_eq!(
+            second.position(),
+            Some(BlobPosition {
+                index: 1,
+                offset: second_offset
+            })
+        );
+        assert_eq!(second.data(), [4, 5]);
+
+        assert!(reader.next().is_none());
+        assert_eq!(reader.next_position().offset, pbf.len() as u64);
+    }
+
+    #[test]
+    fn header_fields() {
+        let blob_header = pbf::BlobHeader {
+            r#type: "CustomBlock".to_string(),
+            indexdata: Some(vec![7, 8]),
+            datasize: 1,
+        }
+        .encode_to_vec();
+
+        let mut pbf = Vec::new();
+        pbf.extend_from_slice(&(blob_header.len() as i32).to_be_bytes());
+        pbf.extend_from_slice(&blob_header);
+        pbf.push(9);
+
+        let raw_block = BlobReader::new(pbf.as_slice()).next().unwrap().unwrap();
+        assert_eq!(raw_block.r#type(), "CustomBlock");
+        assert_eq!(raw_block.index_data(), Some([7, 8].as_slice()));
+        assert_eq!(raw_block.header_size(), blob_header.len());
+        assert_eq!(raw_block.data_size(), 1);
+    }
+
+    #[test]
+    fn limits() {
+        let mut pbf = Vec::new();
+        write_blob(&mut pbf, "OSMData", &[1, 2, 3]);
+        let header_size = pbf.len() - 4 - 3;
+
+        let limits = ReaderLimits {
+            header_size_limit: header_size,
+            ..Default::default()
+        };
+        assert!(matches!(
+            BlobReader::with_limits(pbf.as_slice(), limits).next(),
+            Some(Err(Error::BlobHeaderTooLarge { size, limit })) if size == header_size && limit == header_size
+        ));
+
+        let limits = ReaderLimits {
+            blob_size_limit: 3,
+            ..Default::default()
+        };
+        assert!(matches!(
+            BlobReader::with_limits(pbf.as_slice(), limits).next(),
+            Some(Err(Error::BlobDataTooLarge { size: 3, limit: 3 }))
+        ));
+
+        let limits = ReaderLimits {
+            header_size_limit: header_size + 1,
+            blob_size_limit: 4,
+            ..Default::default()
+        };
+        assert!(matches!(
+            BlobReader::with_limits(pbf.as_slice(), limits).next(),
+            Some(Ok(_))
+        ));
+    }
+
+    #[test]
+    fn stops_after_error() {
+        let mut pbf = Vec::new();
+        write_blob(&mut pbf, "OSMData", &[1, 2, 3]);
+        write_blob(&mut pbf, "OSMData", &[4, 5, 6]);
+        pbf.pop();
+
+        let mut reader = BlobReader::new(pbf.as_slice());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(reader.next(), Some(Err(Error::IoError(_)))));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn truncated_header_size() {
+        let pbf = [0u8, 0];
+
+        let mut reader = BlobReader::new(pbf.as_slice());
+        assert!(matches!(reader.next(), Some(Err(Error::IoError(_)))));
+        assert!(reader.next().is_none());
+    }
 }
 
 /// Blob compression method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CompressionMethod {
     /// LZ4
     Lz4,
@@ -189,36 +948,94 @@ pub enum DecompressionError {
 }
 
 /// Trait for custom decompression support.
+///
+/// A decompressor is owned by its [`BlockParser`], so implementations can keep state between blobs, like
+/// reusable decoder contexts, dictionaries or statistics.
 pub trait Decompressor {
-    /// Decompresses `input` blob into the preallocated `output` slice.
-    fn decompress(method: CompressionMethod, input: &[u8], output: &mut [u8]) -> Result<(), DecompressionError>;
+    /// Decompresses `input` blob into the preallocated `output` slice, returning the number of bytes written.
+    ///
+    /// `output` is one byte larger than the expected uncompressed size, so decompressing into it fully signals that
+    /// the blob is larger than expected. Implementations should stop decompression once `output` is full.
+    fn decompress(
+        &mut self,
+        method: CompressionMethod,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, DecompressionError>;
 }
 
 /// The default blob decompressor.
 ///
-/// Supports ZLib decompression if default features are enabled.
+/// Supports ZLib decompression if the `flate2` feature is enabled (it is by default). Zstandard, LZ4 and
+/// LZMA decompression can be enabled with the `zstd`, `lz4` and `lzma` features respectively.
+#[derive(Debug, Default, Clone, Copy)]
 pub struct DefaultDecompressor;
 
 impl Decompressor for DefaultDecompressor {
-    #[cfg(feature = "default")]
-    fn decompress(method: CompressionMethod, input: &[u8], output: &mut [u8]) -> Result<(), DecompressionError> {
+    #[allow(unused_variables)] // Unused if no decompression features are enabled
+    fn decompress(
+        &mut self,
+        method: CompressionMethod,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, DecompressionError> {
         match method {
-            CompressionMethod::Zlib => {
-                let mut decoder = ZlibDecoder::new(input);
+            #[cfg(feature = "flate2")]
+            CompressionMethod::Zlib => match read_into_slice(ZlibDecoder::new(input), output) {
+                Ok(size) => Ok(size),
+                Err(error) => Err(DecompressionError::InternalError(Box::new(error))),
+            },
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => {
+                let decoder = match zstd::stream::read::Decoder::with_buffer(input) {
+                    Ok(decoder) => decoder,
+                    Err(error) => return Err(DecompressionError::InternalError(Box::new(error))),
+                };
+
+                match read_into_slice(decoder, output) {
+                    Ok(size) => Ok(size),
+                    Err(error) => Err(DecompressionError::InternalError(Box::new(error))),
+                }
+            }
+            #[cfg(feature = "lz4")]
+            CompressionMethod::Lz4 => match lz4_flex::block::decompress_into(input, output) {
+                Ok(size) => Ok(size),
+                Err(lz4_flex::block::DecompressError::OutputTooSmall { .. }) => Ok(output.len()),
+                Err(error) => Err(DecompressionError::InternalError(Box::new(error))),
+            },
+            #[cfg(feature = "lzma")]
+            CompressionMethod::Lzma => {
+                let mut input = input;
+                let output_size = output.len();
+                let mut remaining_output = &mut *output;
 
-                match decoder.read_exact(output) {
-                    Ok(_) => Ok(()),
+                match lzma_rs::lzma_decompress(&mut input, &mut remaining_output) {
+                    Ok(()) => Ok(output_size - remaining_output.len()),
+                    Err(_) if remaining_output.is_empty() => Ok(output_size),
                     Err(error) => Err(DecompressionError::InternalError(Box::new(error))),
                 }
             }
+            #[allow(unreachable_patterns)] // Unreachable if all decompression features are enabled
             _ => Err(DecompressionError::UnsupportedCompression),
         }
     }
+}
 
-    #[cfg(not(feature = "default"))]
-    fn decompress(_method: CompressionMethod, _input: &[u8], _output: &mut [u8]) -> Result<(), DecompressionError> {
-        Err(DecompressionError::UnsupportedCompression)
+/// Reads from `reader` until it's exhausted or `output` is full, returning the number of bytes read.
+#[cfg(any(feature = "flate2", feature = "zstd"))]
+fn read_into_slice(mut reader: impl Read, output: &mut [u8]) -> std::io::Result<usize> {
+    let mut size = 0;
+
+    while size < output.len() {
+        match reader.read(&mut output[size..]) {
+            Ok(0) => break,
+            Ok(read_size) => size += read_size,
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
     }
+
+    Ok(size)
 }
 
 /// Parser with an internal buffer for `RawBlock`s.
@@ -228,7 +1045,9 @@ impl Decompressor for DefaultDecompressor {
 /// alive, avoiding repeated memory allocations.
 pub struct BlockParser<D: Decompressor = DefaultDecompressor> {
     block_buffer: Vec<u8>,
-    decompressor: std::marker::PhantomData<D>,
+    decompressor: D,
+    limits: ReaderLimits,
+    strict: bool,
 }
 
 impl Default for BlockParser {
@@ -237,64 +1056,176 @@ impl Default for BlockParser {
     }
 }
 
-impl<D: Decompressor> BlockParser<D> {
-    /// Creates a new `BlockParser`.
+impl<D: Decompressor + Default> BlockParser<D> {
+    /// Creates a new `BlockParser` with a default constructed decompressor.
     pub fn new() -> Self {
+        Self::with_decompressor(D::default())
+    }
+}
+
+impl<D: Decompressor> BlockParser<D> {
+    /// Creates a new `BlockParser` which uses `decompressor` to decompress blobs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rosm_pbf_reader::{BlockParser, CompressionMethod, DecompressionError, Decompressor};
+    ///
+    /// #[derive(Default)]
+    /// struct CountingDecompressor {
+    ///     blob_count: usize,
+    /// }
+    ///
+    /// impl Decompressor for CountingDecompressor {
+    ///     fn decompress(
+    ///         &mut self,
+    ///         method: CompressionMethod,
+    ///         input: &[u8],
+    ///         output: &mut [u8],
+    ///     ) -> Result<usize, DecompressionError> {
+    ///         self.blob_count += 1;
+    ///         Err(DecompressionError::UnsupportedCompression)
+    ///     }
+    /// }
+    ///
+    /// let parser = BlockParser::with_decompressor(CountingDecompressor::default());
+    /// assert_eq!(parser.decompressor().blob_count, 0);
+    /// ```
+    pub fn with_decompressor(decompressor: D) -> Self {
         Self {
             block_buffer: Vec::new(),
-            decompressor: Default::default(),
+            decompressor,
+            limits: ReaderLimits::default(),
+            strict: false,
         }
     }
 
+    /// Returns the size limits of this parser.
+    pub fn limits(&self) -> &ReaderLimits {
+        &self.limits
+    }
+
+    /// Sets the size limits of this parser. Only [`ReaderLimits::uncompressed_size_limit`] is used during parsing.
+    pub fn set_limits(&mut self, limits: ReaderLimits) {
+        self.limits = limits;
+    }
+
+    /// Returns whether this parser is strict. See [`BlockParser::set_strict`].
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Sets whether this parser is strict. A strict parser returns [`Error::UnsupportedFeature`] when parsing a header
+    /// block which requires a feature not supported by the library, as mandated by the PBF format. Parsers aren't
+    /// strict by default.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Returns a reference to the decompressor of this parser.
+    pub fn decompressor(&self) -> &D {
+        &self.decompressor
+    }
+
+    /// Returns a mutable reference to the decompressor of this parser.
+    pub fn decompressor_mut(&mut self) -> &mut D {
+        &mut self.decompressor
+    }
+
     /// Parses `raw_block` into a header, primitive or unknown block.
     ///
     /// # Errors
     ///
     /// Will return `Err` if an error occurs during PBF parsing, decompression or validation.
     #[allow(deprecated)]
-    pub fn parse_block(&mut self, raw_block: RawBlock) -> Result<Block, Error> {
+    pub fn parse_block(&mut self, raw_block: RawBlock<'_>) -> Result<Block<'_>, Error> {
         let blob = match pbf::Blob::decode(&*raw_block.data) {
             Ok(blob) => blob,
             Err(error) => return Err(Error::PbfParseError(error)),
         };
 
-        if let Some(uncompressed_size) = blob.raw_size {
-            let uncompressed_size: usize = uncompressed_size.try_into().map_err(|_err| Error::InvalidBlobData)?;
-            self.block_buffer.resize_with(uncompressed_size, Default::default);
-        }
+        let raw_size: Option<usize> = match blob.raw_size {
+            Some(raw_size) => Some(raw_size.try_into().map_err(|_err| Error::InvalidBlobData)?),
+            None => None,
+        };
 
-        if let Some(blob_data) = blob.data {
-            match blob_data {
-                pbf::blob::Data::Raw(raw_data) => self.block_buffer.extend_from_slice(&raw_data),
-                pbf::blob::Data::ZlibData(zlib_data) => {
-                    if let Err(error) = D::decompress(CompressionMethod::Zlib, &zlib_data, &mut self.block_buffer) {
-                        return Err(Error::DecompressionError(error));
-                    }
-                }
-                pbf::blob::Data::Lz4Data(lz4_data) => {
-                    if let Err(error) = D::decompress(CompressionMethod::Lz4, &lz4_data, &mut self.block_buffer) {
-                        return Err(Error::DecompressionError(error));
-                    }
+        let (method, compressed_data) = match blob.data {
+            Some(pbf::blob::Data::Raw(raw_data)) => {
+                if raw_data.len() >= self.limits.uncompressed_size_limit {
+                    return Err(Error::UncompressedBlobTooLarge {
+                        size: raw_data.len(),
+                        limit: self.limits.uncompressed_size_limit,
+                    });
                 }
-                pbf::blob::Data::LzmaData(lzma_data) => {
-                    if let Err(error) = D::decompress(CompressionMethod::Lzma, &lzma_data, &mut self.block_buffer) {
-                        return Err(Error::DecompressionError(error));
-                    }
-                }
-                pbf::blob::Data::ZstdData(zstd_data) => {
-                    if let Err(error) = D::decompress(CompressionMethod::Zstd, &zstd_data, &mut self.block_buffer) {
-                        return Err(Error::DecompressionError(error));
+
+                if let Some(raw_size) = raw_size {
+                    if raw_size != raw_data.len() {
+                        return Err(Error::RawSizeMismatch {
+                            expected: raw_size,
+                            actual: Some(raw_data.len()),
+                        });
                     }
                 }
-                pbf::blob::Data::ObsoleteBzip2Data(_) => return Err(Error::InvalidBlobData),
+
+                self.block_buffer.clear();
+                self.block_buffer.extend_from_slice(&raw_data);
+
+                return self.parse_block_buffer(&raw_block);
             }
-        } else {
+            Some(pbf::blob::Data::ZlibData(zlib_data)) => (CompressionMethod::Zlib, zlib_data),
+            Some(pbf::blob::Data::Lz4Data(lz4_data)) => (CompressionMethod::Lz4, lz4_data),
+            Some(pbf::blob::Data::LzmaData(lzma_data)) => (CompressionMethod::Lzma, lzma_data),
+            Some(pbf::blob::Data::ZstdData(zstd_data)) => (CompressionMethod::Zstd, zstd_data),
+            Some(pbf::blob::Data::ObsoleteBzip2Data(_)) | None => return Err(Error::InvalidBlobData),
+        };
+
+        // The uncompressed size is required to preallocate the buffer and to protect against decompression bombs
+        let Some(raw_size) = raw_size else {
             return Err(Error::InvalidBlobData);
+        };
+
+        if raw_size >= self.limits.uncompressed_size_limit {
+            return Err(Error::UncompressedBlobTooLarge {
+                size: raw_size,
+                limit: self.limits.uncompressed_size_limit,
+            });
+        }
+
+        // One extra byte is used to detect data that decompresses to more bytes than `raw_size`
+        self.block_buffer.resize(raw_size + 1, 0);
+
+        let uncompressed_size = match self
+            .decompressor
+            .decompress(method, &compressed_data, &mut self.block_buffer)
+        {
+            Ok(uncompressed_size) => uncompressed_size,
+            Err(error) => return Err(Error::DecompressionError(error)),
+        };
+
+        if uncompressed_size != raw_size {
+            return Err(Error::RawSizeMismatch {
+                expected: raw_size,
+                actual: (uncompressed_size < raw_size).then_some(uncompressed_size),
+            });
         }
 
-        match raw_block.r#type {
+        self.block_buffer.truncate(raw_size);
+
+        self.parse_block_buffer(&raw_block)
+    }
+
+    fn parse_block_buffer(&self, raw_block: &RawBlock<'_>) -> Result<Block<'_>, Error> {
+        match BlockType::from(raw_block.r#type()) {
             BlockType::Header => match pbf::HeaderBlock::decode(&*self.block_buffer) {
-                Ok(header_block) => Ok(Block::Header(header_block)),
+                Ok(header_block) => {
+                    if self.strict {
+                        if let Some(feature) = header::Header::new(&header_block).unsupported_features().next() {
+                            return Err(Error::UnsupportedFeature(feature.to_string()));
+                        }
+                    }
+
+                    Ok(Block::Header(header_block))
+                }
                 Err(error) => Err(Error::PbfParseError(error)),
             },
             BlockType::Primitive => match pbf::PrimitiveBlock::decode(&*self.block_buffer) {
@@ -306,6 +1237,271 @@ impl<D: Decompressor> BlockParser<D> {
     }
 }
 
+#[cfg(test)]
+mod block_parser_tests {
+    use super::*;
+    use crate::test_utils::test_primitive_block;
+
+    fn raw_primitive_block(data: pbf::blob::Data, raw_size: usize) -> RawBlock<'static> {
+        let blob = pbf::Blob {
+            raw_size: Some(raw_size as i32),
+            data: Some(data),
+        };
+
+        RawBlock {
+            header: pbf::BlobHeader {
+                r#type: "OSMData".to_string(),
+                ..Default::default()
+            },
+            header_size: 0,
+            data: Cow::Owned(blob.encode_to_vec()),
+            position: None,
+        }
+    }
+
+    #[test]
+    fn uncompressed_size_limit() {
+        let encoded_block = test_primitive_block().encode_to_vec();
+
+        let mut parser = BlockParser::default();
+        parser.set_limits(ReaderLimits {
+            uncompressed_size_limit: encoded_block.len(),
+            ..Default::default()
+        });
+
+        let raw_block = raw_primitive_block(pbf::blob::Data::ZlibData(vec![]), encoded_block.len());
+        assert!(matches!(
+            parser.parse_block(raw_block),
+            Err(Error::UncompressedBlobTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn raw_primitive_block_buffer_reuse() {
+        let block = test_primitive_block();
+        let encoded_block = block.encode_to_vec();
+
+        let mut parser = BlockParser::default();
+        for _ in 0..2 {
+            let raw_block = raw_primitive_block(pbf::blob::Data::Raw(encoded_block.clone()), encoded_block.len());
+            match parser.parse_block(raw_block) {
+                Ok(Block::Primitive(parsed_block)) => assert_eq!(parsed_block, block),
+                _ => panic!("expected a primitive block"),
+            }
+        }
+
+        let raw_block = raw_primitive_block(pbf::blob::Data::Raw(encoded_block.clone()), encoded_block.len() + 1);
+        assert!(matches!(
+            parser.parse_block(raw_block),
+            Err(Error::RawSizeMismatch { actual: Some(actual), .. }) if actual == encoded_block.len()
+        ));
+    }
+
+    #[test]
+    fn strict_required_features() {
+        let header_block = pbf::HeaderBlock {
+            required_features: vec!["OsmSchema-V0.6".to_string(), "Foo".to_string()],
+            ..Default::default()
+        };
+        let encoded_block = header_block.encode_to_vec();
+
+        let raw_header_block = || {
+            let mut raw_block = raw_primitive_block(pbf::blob::Data::Raw(encoded_block.clone()), encoded_block.len());
+            raw_block.header.r#type = "OSMHeader".to_string();
+            raw_block
+        };
+
+        let mut parser = BlockParser::default();
+        assert!(matches!(parser.parse_block(raw_header_block()), Ok(Block::Header(_))));
+
+        parser.set_strict(true);
+        assert!(matches!(
+            parser.parse_block(raw_header_block()),
+            Err(Error::UnsupportedFeature(feature)) if feature == "Foo"
+        ));
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn zlib_raw_size_mismatch() {
+        use flate2::write::ZlibEncoder;
+
+        let block = test_primitive_block();
+        let encoded_block = block.encode_to_vec();
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&encoded_block).unwrap();
+        let compressed_block = encoder.finish().unwrap();
+
+        let mut parser = BlockParser::default();
+
+        let raw_block = raw_primitive_block(pbf::blob::Data::ZlibData(compressed_block.clone()), encoded_block.len());
+        match parser.parse_block(raw_block) {
+            Ok(Block::Primitive(parsed_block)) => assert_eq!(parsed_block, block),
+            _ => panic!("expected a primitive block"),
+        }
+
+        for (raw_size, actual) in [
+            (encoded_block.len() - 1, None),
+            (encoded_block.len() + 1, Some(encoded_block.len())),
+        ] {
+            let raw_block = raw_primitive_block(pbf::blob::Data::ZlibData(compressed_block.clone()), raw_size);
+            assert!(matches!(
+                parser.parse_block(raw_block),
+                Err(Error::RawSizeMismatch { expected, actual: a }) if expected == raw_size && a == actual
+            ));
+        }
+
+        let mut raw_block = raw_primitive_block(pbf::blob::Data::ZlibData(compressed_block), 0);
+        raw_block.data = Cow::Owned(
+            pbf::Blob {
+                raw_size: None,
+                data: pbf::Blob::decode(&*raw_block.data).unwrap().data,
+            }
+            .encode_to_vec(),
+        );
+        assert!(matches!(parser.parse_block(raw_block), Err(Error::InvalidBlobData)));
+    }
+
+    #[test]
+    fn stateful_decompressor() {
+        #[derive(Default)]
+        struct CopyingDecompressor {
+            methods: Vec<CompressionMethod>,
+        }
+
+        impl Decompressor for CopyingDecompressor {
+            fn decompress(
+                &mut self,
+                method: CompressionMethod,
+                input: &[u8],
+                output: &mut [u8],
+            ) -> Result<usize, DecompressionError> {
+                self.methods.push(method);
+                let size = input.len().min(output.len());
+                output[..size].copy_from_slice(&input[..size]);
+                Ok(size)
+            }
+        }
+
+        let block = test_primitive_block();
+        let encoded_block = block.encode_to_vec();
+
+        let mut parser = BlockParser::with_decompressor(CopyingDecompressor::default());
+        for data in [
+            pbf::blob::Data::Lz4Data(encoded_block.clone()),
+            pbf::blob::Data::ZstdData(encoded_block.clone()),
+        ] {
+            let raw_block = raw_primitive_block(data, encoded_block.len());
+            match parser.parse_block(raw_block) {
+                Ok(Block::Primitive(parsed_block)) => assert_eq!(parsed_block, block),
+                _ => panic!("expected a primitive block"),
+            }
+        }
+
+        assert_eq!(
+            parser.decompressor().methods,
+            [CompressionMethod::Lz4, CompressionMethod::Zstd]
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_primitive_block() {
+        let block = test_primitive_block();
+        let encoded_block = block.encode_to_vec();
+        let compressed_block = zstd::bulk::compress(&encoded_block, 3).unwrap();
+        let raw_block = raw_primitive_block(pbf::blob::Data::ZstdData(compressed_block), encoded_block.len());
+
+        let mut parser = BlockParser::default();
+        match parser.parse_block(raw_block) {
+            Ok(Block::Primitive(parsed_block)) => assert_eq!(parsed_block, block),
+            _ => panic!("expected a primitive block"),
+        }
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn lz4_primitive_block() {
+        let block = test_primitive_block();
+        let encoded_block = block.encode_to_vec();
+        let compressed_block = lz4_flex::block::compress(&encoded_block);
+        let raw_block = raw_primitive_block(pbf::blob::Data::Lz4Data(compressed_block), encoded_block.len());
+
+        let mut parser = BlockParser::default();
+        match parser.parse_block(raw_block) {
+            Ok(Block::Primitive(parsed_block)) => assert_eq!(parsed_block, block),
+            _ => panic!("expected a primitive block"),
+        }
+    }
+
+    #[cfg(feature = "lzma")]
+    #[test]
+    fn lzma_primitive_block() {
+        let block = test_primitive_block();
+        let encoded_block = block.encode_to_vec();
+        let mut compressed_block = Vec::new();
+        lzma_rs::lzma_compress(&mut encoded_block.as_slice(), &mut compressed_block).unwrap();
+        let raw_block = raw_primitive_block(pbf::blob::Data::LzmaData(compressed_block), encoded_block.len());
+
+        let mut parser = BlockParser::default();
+        match parser.parse_block(raw_block) {
+            Ok(Block::Primitive(parsed_block)) => assert_eq!(parsed_block, block),
+            _ => panic!("expected a primitive block"),
+        }
+    }
+
+    #[cfg(feature = "lzma")]
+    #[test]
+    fn lzma_raw_size_mismatch() {
+        let encoded_block = test_primitive_block().encode_to_vec();
+        let mut compressed_block = Vec::new();
+        lzma_rs::lzma_compress(&mut encoded_block.as_slice(), &mut compressed_block).unwrap();
+
+        let mut parser = BlockParser::default();
+
+        let raw_block = raw_primitive_block(
+            pbf::blob::Data::LzmaData(compressed_block.clone()),
+            encoded_block.len() + 1,
+        );
+        assert!(matches!(
+            parser.parse_block(raw_block),
+            Err(Error::RawSizeMismatch { expected, actual: Some(actual) })
+                if expected == encoded_block.len() + 1 && actual == encoded_block.len()
+        ));
+
+        let raw_block = raw_primitive_block(pbf::blob::Data::LzmaData(compressed_block), encoded_block.len() - 1);
+        assert!(matches!(
+            parser.parse_block(raw_block),
+            Err(Error::RawSizeMismatch { actual: None, .. })
+        ));
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn unsupported_zstd_data() {
+        let encoded_block = test_primitive_block().encode_to_vec();
+        let raw_block = raw_primitive_block(pbf::blob::Data::ZstdData(encoded_block.clone()), encoded_block.len());
+
+        let mut parser = BlockParser::default();
+        assert!(matches!(
+            parser.parse_block(raw_block),
+            Err(Error::DecompressionError(DecompressionError::UnsupportedCompression))
+        ));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn invalid_zstd_data() {
+        let raw_block = raw_primitive_block(pbf::blob::Data::ZstdData(vec![1, 2, 3, 4]), 16);
+
+        let mut parser = BlockParser::default();
+        assert!(matches!(
+            parser.parse_block(raw_block),
+            Err(Error::DecompressionError(DecompressionError::InternalError(_)))
+        ));
+    }
+}
+
 /// Generalized implementation for reading normal or densely encoded tags from string tables.
 ///
 /// Use [`new_tag_reader`] or [`dense::new_dense_tag_reader`] to construct it.
@@ -327,28 +1523,13 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         match self.iter.next() {
             Some((key, value)) => {
-                let decode_string = |index: usize| -> Result<&str, Error> {
-                    if let Some(bytes) = self.string_table.s.get(index) {
-                        if let Ok(utf8_string) = str::from_utf8(bytes) {
-                            Ok(utf8_string)
-                        } else {
-                            Err(Error::LogicError(format!("string at index {index} is not valid UTF-8")))
-                        }
-                    } else {
-                        Err(Error::LogicError(format!(
-                            "string table index {index} is out of bounds ({})",
-                            self.string_table.s.len()
-                        )))
-                    }
-                };
-
                 let key = match key {
-                    Ok(key_idx) => decode_string(key_idx),
+                    Ok(key_idx) => decode_string(self.string_table, key_idx),
                     Err(error) => Err(error),
                 };
 
                 let value = match value {
-                    Ok(value_idx) => decode_string(value_idx),
+                    Ok(value_idx) => decode_string(self.string_table, value_idx),
                     Err(error) => Err(error),
                 };
 
@@ -359,6 +1540,23 @@ where
     }
 }
 
+/// Returns the string at `index` of `string_table`, or an error if the index is out of bounds or the string is not
+/// valid UTF-8.
+pub(crate) fn decode_string(string_table: &pbf::StringTable, index: usize) -> Result<&str, Error> {
+    if let Some(bytes) = string_table.s.get(index) {
+        if let Ok(utf8_string) = str::from_utf8(bytes) {
+            Ok(utf8_string)
+        } else {
+            Err(Error::LogicError(format!("string at index {index} is not valid UTF-8")))
+        }
+    } else {
+        Err(Error::LogicError(format!(
+            "string table index {index} is out of bounds ({})",
+            string_table.s.len()
+        )))
+    }
+}
+
 /// Constructs a new `TagReader` from key and value index slices, and a corresponding string table.
 ///
 /// # Examples
@@ -383,10 +1581,56 @@ pub fn new_tag_reader<'a>(
 ) -> TagReader<'a, impl Iterator<Item = (Result<usize, Error>, Result<usize, Error>)> + 'a> {
     TagReader {
         string_table,
-        iter: key_indices
-            .iter()
-            .map(|i| Ok(*i as usize))
-            .zip(value_indices.iter().map(|i| Ok(*i as usize))),
+        iter: TagIndices::sparse(key_indices, value_indices),
+    }
+}
+
+/// Iterator over the (key, value) string table index pairs of normal or densely encoded tags.
+///
+/// Used by the [`TagReader`]s returned by the element views of the [`element`] module.
+#[derive(Clone)]
+pub struct TagIndices<'a> {
+    inner: TagIndicesInner<'a>,
+}
+
+#[derive(Clone)]
+enum TagIndicesInner<'a> {
+    Sparse(std::iter::Zip<std::slice::Iter<'a, u32>, std::slice::Iter<'a, u32>>),
+    Dense(std::slice::ChunksExact<'a, i32>),
+}
+
+impl<'a> TagIndices<'a> {
+    pub(crate) fn sparse(key_indices: &'a [u32], value_indices: &'a [u32]) -> Self {
+        TagIndices {
+            inner: TagIndicesInner::Sparse(key_indices.iter().zip(value_indices.iter())),
+        }
+    }
+
+    pub(crate) fn dense(key_value_indices: &'a [i32]) -> Self {
+        TagIndices {
+            inner: TagIndicesInner::Dense(key_value_indices.chunks_exact(2)),
+        }
+    }
+}
+
+impl Iterator for TagIndices<'_> {
+    type Item = (Result<usize, Error>, Result<usize, Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            TagIndicesInner::Sparse(iter) => iter.next().map(|(key, value)| (Ok(*key as usize), Ok(*value as usize))),
+            TagIndicesInner::Dense(iter) => iter.next().map(|s| {
+                let convert_idx = |index: i32| -> Result<usize, Error> {
+                    if let Ok(index) = TryInto::<usize>::try_into(index) {
+                        Ok(index)
+                    } else {
+                        Err(Error::LogicError(format!("string table index {index} is invalid")))
+                    }
+                };
+
+                (convert_idx(s[0]), convert_idx(s[1]))
+            }),
+        }
     }
 }
 
@@ -413,6 +1657,8 @@ mod tag_reader_tests {
 }
 
 /// Utility for reading delta-encoded values directly, like [`pbf::Way::refs`] and [`pbf::Relation::memids`].
+///
+/// Returns an error (and then stops) if a decoded value overflows `T`.
 pub struct DeltaValueReader<'a, T> {
     remaining: &'a [T],
     accumulated: T,
@@ -434,7 +1680,7 @@ where
     ///         for way in &group.ways {
     ///             let refs = DeltaValueReader::new(&way.refs);
     ///             for node_id in refs {
-    ///                 println!("{}", node_id);
+    ///                 println!("{}", node_id.unwrap());
     ///             }
     ///         }
     ///     }
@@ -450,17 +1696,25 @@ where
 
 impl<T> Iterator for DeltaValueReader<'_, T>
 where
-    T: std::ops::AddAssign + std::clone::Clone,
+    T: Copy + Into<i128> + TryFrom<i128>,
 {
-    type Item = T;
+    /// A value, or an error if decoding it overflows
+    type Item = Result<T, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some((first, elements)) = self.remaining.split_first() {
-            self.accumulated += first.clone();
-            self.remaining = elements;
-            Some(self.accumulated.clone())
-        } else {
-            None
+        let (first, elements) = self.remaining.split_first()?;
+
+        let value = self.accumulated.into() + (*first).into();
+        match T::try_from(value) {
+            Ok(value) => {
+                self.accumulated = value;
+                self.remaining = elements;
+                Some(Ok(value))
+            }
+            Err(_) => {
+                self.remaining = &[];
+                Some(Err(Error::LogicError(format!("delta decoded value {value} overflows"))))
+            }
         }
     }
 }
@@ -472,16 +1726,22 @@ mod delta_value_reader_tests {
     #[test]
     fn empty_input() {
         let mut reader = DeltaValueReader::new(&[] as &[i64]);
-        assert_eq!(reader.next(), None);
+        assert!(reader.next().is_none());
     }
 
     #[test]
     fn valid_input() {
         let values = [10, -1, 4, -2];
+        let reader = DeltaValueReader::new(&values);
+        assert_eq!(reader.collect::<Result<Vec<_>, _>>().unwrap(), [10, 9, 13, 11]);
+    }
+
+    #[test]
+    fn overflow() {
+        let values = [i64::MAX, 1, 1];
         let mut reader = DeltaValueReader::new(&values);
-        assert_eq!(reader.next(), Some(10));
-        assert_eq!(reader.next(), Some(9));
-        assert_eq!(reader.next(), Some(13));
-        assert_eq!(reader.next(), Some(11));
+        assert_eq!(reader.next().unwrap().unwrap(), i64::MAX);
+        assert!(matches!(reader.next(), Some(Err(Error::LogicError(_)))));
+        assert!(reader.next().is_none());
     }
 }
@@ -0,0 +1,680 @@
+//! Parallel block parsing on worker threads.
+//!
+//! [`ParallelBlockReader`] uses its own threads. With the optional `rayon` feature, blocks can also be parsed as a
+//! Rayon `ParallelIterator` with `par_parse_blocks`, or reduced with `par_map_reduce`.
+
+use crate::{pbf, Block, BlockParser, Decompressor, Error, RawBlock, ReaderLimits};
+
+#[cfg(feature = "rayon")]
+use rayon::iter::{ParallelBridge, ParallelIterator};
+
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Owned version of [`Block`], which can be sent between threads.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedBlock {
+    /// A raw `OSMHeader` block.
+    Header(pbf::HeaderBlock),
+    /// A raw `OSMData` (primitive) block.
+    Primitive(pbf::PrimitiveBlock),
+    /// An unknown block.
+    Unknown(Vec<u8>),
+}
+
+impl From<Block<'_>> for ParsedBlock {
+    fn from(block: Block<'_>) -> Self {
+        match block {
+            Block::Header(header_block) => ParsedBlock::Header(header_block),
+            Block::Primitive(primitive_block) => ParsedBlock::Primitive(primitive_block),
+            Block::Unknown(data) => ParsedBlock::Unknown(data.to_vec()),
+        }
+    }
+}
+
+/// Options of a [`ParallelBlockReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParallelOptions {
+    /// Number of worker threads parsing blocks. Values smaller than 1 are treated as 1.
+    pub thread_count: usize,
+    /// Maximum number of blocks read, but not yet returned by the reader. Values smaller than 1 are treated as 1.
+    ///
+    /// This bounds the memory used by blocks waiting to be parsed or to be returned. In ordered mode, a slow
+    /// block stalls reading once this many blocks are waiting behind it.
+    pub max_blocks_in_flight: usize,
+    /// Whether blocks are returned in input order. Otherwise, blocks are returned as soon as they're parsed.
+    pub ordered: bool,
+    /// Limits used by the block parsers of the worker threads. Ignored by [`ParallelBlockReader::with_parser`].
+    pub limits: ReaderLimits,
+    /// Whether the block parsers of the worker threads are strict. See [`BlockParser::set_strict`]. Ignored by
+    /// [`ParallelBlockReader::with_parser`].
+    pub strict: bool,
+}
+
+impl Default for ParallelOptions {
+    /// Uses as many threads as [`std::thread::available_parallelism`], with 4 blocks in flight per thread, in
+    /// input order, with non-strict parsers.
+    fn default() -> Self {
+        let thread_count = thread::available_parallelism().map_or(1, |count| count.get());
+
+        Self {
+            thread_count,
+            max_blocks_in_flight: 4 * thread_count,
+            ordered: true,
+            limits: ReaderLimits::default(),
+            strict: false,
+        }
+    }
+}
+
+type Job = (usize, RawBlock<'static>);
+type JobResult = (usize, Result<ParsedBlock, Error>);
+
+/// Iterator parsing the blocks of a blob iterator on multiple worker threads.
+///
+/// Blobs are read on a dedicated thread, then parsed by the worker threads, each of which reuses its own
+/// [`BlockParser`]. Errors of the blob iterator are returned in place of the failed blob. Panics of the blob iterator
+/// and the block parsers are returned as [`Error::LogicError`]; a panicking blob iterator ends the reader.
+///
+/// Dropping the reader stops reading new blobs; its threads exit after finishing their current work.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::parallel::{ParallelBlockReader, ParallelOptions, ParsedBlock};
+/// use rosm_pbf_reader::BlobReader;
+///
+/// use std::fs::File;
+///
+/// let file = File::open("some.osm.pbf").unwrap();
+/// let blocks = ParallelBlockReader::new(BlobReader::new(file), ParallelOptions::default());
+///
+/// for block in blocks {
+///     if let ParsedBlock::Primitive(primitive_block) = block.unwrap() {
+///         println!("block has {} groups", primitive_block.primitivegroup.len());
+///     }
+/// }
+/// ```
+pub struct ParallelBlockReader {
+    results: Receiver<JobResult>,
+    permits: Receiver<()>,
+    ordered: bool,
+    pending: BTreeMap<usize, Result<ParsedBlock, Error>>,
+    next_sequence: usize,
+}
+
+impl ParallelBlockReader {
+    /// Starts reading blobs from `blobs` and parsing them on worker threads.
+    pub fn new<Blobs>(blobs: Blobs, options: ParallelOptions) -> Self
+    where
+        Blobs: Iterator<Item = Result<RawBlock<'static>, Error>> + Send + 'static,
+    {
+        let ParallelOptions { limits, strict, .. } = options;
+
+        Self::with_parser(blobs, options, move || {
+            let mut block_parser = BlockParser::default();
+            block_parser.set_limits(limits);
+            block_parser.set_strict(strict);
+            block_parser
+        })
+    }
+
+    /// Starts reading blobs from `blobs` and parsing them on worker threads, with block parsers created by
+    /// `new_parser` (once per worker thread).
+    ///
+    /// This allows using custom or configured decompressors. [`ParallelOptions::limits`] and
+    /// [`ParallelOptions::strict`] are ignored, the parsers returned by `new_parser` are used as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rosm_pbf_reader::parallel::{ParallelBlockReader, ParallelOptions};
+    /// use rosm_pbf_reader::{BlobReader, BlockParser, DefaultDecompressor};
+    ///
+    /// use std::fs::File;
+    ///
+    /// let file = File::open("some.osm.pbf").unwrap();
+    /// let blocks = ParallelBlockReader::with_parser(BlobReader::new(file), ParallelOptions::default(), || {
+    ///     BlockParser::with_decompressor(DefaultDecompressor)
+    /// });
+    /// ```
+    pub fn with_parser<Blobs, D, NewParser>(blobs: Blobs, options: ParallelOptions, new_parser: NewParser) -> Self
+    where
+        Blobs: Iterator<Item = Result<RawBlock<'static>, Error>> + Send + 'static,
+        D: Decompressor,
+        NewParser: Fn() -> BlockParser<D> + Send + Sync + 'static,
+    {
+        let thread_count = options.thread_count.max(1);
+        let max_blocks_in_flight = options.max_blocks_in_flight.max(1);
+
+        // The reader thread sends a permit before each blob, and one is received for each returned block, so the
+        // reader thread blocks while too many blocks are in flight
+        let (permit_sender, permits) = mpsc::sync_channel(max_blocks_in_flight);
+        let (job_sender, job_receiver) = mpsc::sync_channel(max_blocks_in_flight);
+        let (result_sender, results) = mpsc::channel();
+
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let new_parser = Arc::new(new_parser);
+
+        for _ in 0..thread_count {
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+            let new_parser = Arc::clone(&new_parser);
+            thread::spawn(move || parse_blocks(&job_receiver, &result_sender, &*new_parser));
+        }
+
+        thread::spawn(move || read_blobs(blobs, &permit_sender, &job_sender, &result_sender));
+
+        Self {
+            results,
+            permits,
+            ordered: options.ordered,
+            pending: BTreeMap::new(),
+            next_sequence: 0,
+        }
+    }
+
+    fn release(&mut self, result: Result<ParsedBlock, Error>) -> Option<Result<ParsedBlock, Error>> {
+        let _ = self.permits.try_recv();
+        Some(result)
+    }
+}
+
+fn read_blobs<Blobs>(
+    mut blobs: Blobs,
+    permit_sender: &SyncSender<()>,
+    job_sender: &SyncSender<Job>,
+    result_sender: &mpsc::Sender<JobResult>,
+) where
+    Blobs: Iterator<Item = Result<RawBlock<'static>, Error>>,
+{
+    for sequence in 0.. {
+        let (blob, panicked) = match panic::catch_unwind(AssertUnwindSafe(|| blobs.next())) {
+            Ok(Some(blob)) => (blob, false),
+            Ok(None) => return,
+            Err(payload) => (Err(Error::LogicError(panic_message("blob iterator", &*payload))), true),
+        };
+
+        if permit_sender.send(()).is_err() {
+            // The reader was dropped
+            return;
+        }
+
+        let sent = match blob {
+            Ok(raw_block) => job_sender.send((sequence, raw_block)).is_ok(),
+            Err(error) => result_sender.send((sequence, Err(error))).is_ok(),
+        };
+
+        if !sent || panicked {
+            return;
+        }
+    }
+}
+
+fn parse_blocks<D: Decompressor>(
+    job_receiver: &Mutex<Receiver<Job>>,
+    result_sender: &mpsc::Sender<JobResult>,
+    new_parser: &dyn Fn() -> BlockParser<D>,
+) {
+    // A result must be sent for each job, otherwise the reader would wait for it forever in ordered mode, so panics
+    // are returned as errors, even if the parser couldn't be created
+    let mut block_parser = panic::catch_unwind(AssertUnwindSafe(new_parser))
+        .map_err(|payload| panic_message("block parser factory", &*payload));
+
+    loop {
+        let job = match job_receiver.lock() {
+            Ok(job_receiver) => job_receiver.recv(),
+            Err(_) => return,
+        };
+
+        // The reader thread finished
+        let Ok((sequence, raw_block)) = job else {
+            return;
+        };
+
+        let result = match &mut block_parser {
+            Ok(block_parser) => panic::catch_unwind(AssertUnwindSafe(|| {
+                block_parser.parse_block(raw_block).map(ParsedBlock::from)
+            }))
+            .unwrap_or_else(|payload| Err(Error::LogicError(panic_message("block parser", &*payload)))),
+            Err(message) => Err(Error::LogicError(message.clone())),
+        };
+
+        if result_sender.send((sequence, result)).is_err() {
+            return;
+        }
+    }
+}
+
+fn panic_message(source: &str, payload: &(dyn std::any::Any + Send)) -> String {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    };
+
+    format!("{source} panicked: {message}")
+}
+
+impl Iterator for ParallelBlockReader {
+    type Item = Result<ParsedBlock, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.ordered {
+                if let Some(result) = self.pending.remove(&self.next_sequence) {
+                    self.next_sequence += 1;
+                    return self.release(result);
+                }
+            }
+
+            match self.results.recv() {
+                Ok((_, result)) if !self.ordered => return self.release(result),
+                Ok((sequence, result)) => {
+                    self.pending.insert(sequence, result);
+                }
+                // All threads finished, after sending a result for each blob
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+/// Parses the blocks of a blob iterator as a Rayon [`ParallelIterator`].
+///
+/// Blobs are read from `blobs` by one thread at a time, and parsed on Rayon's thread pool. A [`BlockParser`] is created
+/// for each Rayon job (which may parse several blocks), not for each thread. Blocks are returned in no particular
+/// order.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::parallel::{par_parse_blocks, ParsedBlock};
+/// use rosm_pbf_reader::BlobReader;
+///
+/// use rayon::iter::ParallelIterator;
+///
+/// use std::fs::File;
+///
+/// let file = File::open("some.osm.pbf").unwrap();
+///
+/// let group_count: usize = par_parse_blocks(BlobReader::new(file))
+///     .map(|block| match block.unwrap() {
+///         ParsedBlock::Primitive(primitive_block) => primitive_block.primitivegroup.len(),
+///         _ => 0,
+///     })
+///     .sum();
+/// ```
+#[cfg(feature = "rayon")]
+pub fn par_parse_blocks<Blobs>(blobs: Blobs) -> impl ParallelIterator<Item = Result<ParsedBlock, Error>>
+where
+    Blobs: Iterator<Item = Result<RawBlock<'static>, Error>> + Send,
+{
+    par_parse_blocks_with_limits(blobs, ReaderLimits::default())
+}
+
+/// Parses the blocks of a blob iterator as a Rayon [`ParallelIterator`], like [`par_parse_blocks`], but with custom
+/// size limits.
+#[cfg(feature = "rayon")]
+pub fn par_parse_blocks_with_limits<Blobs>(
+    blobs: Blobs,
+    limits: ReaderLimits,
+) -> impl ParallelIterator<Item = Result<ParsedBlock, Error>>
+where
+    Blobs: Iterator<Item = Result<RawBlock<'static>, Error>> + Send,
+{
+    par_parse_blocks_with_parser(blobs, move || {
+        let mut block_parser = BlockParser::default();
+        block_parser.set_limits(limits);
+        block_parser
+    })
+}
+
+/// Parses the blocks of a blob iterator as a Rayon [`ParallelIterator`], like [`par_parse_blocks`], but with block
+/// parsers created by `new_parser`.
+///
+/// This allows using strict parsers, or custom or configured decompressors. `new_parser` is called once per Rayon job,
+/// so it may be called many more times than the number of threads.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::parallel::par_parse_blocks_with_parser;
+/// use rosm_pbf_reader::{BlobReader, BlockParser};
+///
+/// use rayon::iter::ParallelIterator;
+///
+/// use std::fs::File;
+///
+/// let file = File::open("some.osm.pbf").unwrap();
+///
+/// let block_count = par_parse_blocks_with_parser(BlobReader::new(file), || {
+///     let mut block_parser = BlockParser::default();
+///     block_parser.set_strict(true);
+///     block_parser
+/// })
+/// .count();
+/// ```
+#[cfg(feature = "rayon")]
+pub fn par_parse_blocks_with_parser<Blobs, D, NewParser>(
+    blobs: Blobs,
+    new_parser: NewParser,
+) -> impl ParallelIterator<Item = Result<ParsedBlock, Error>>
+where
+    Blobs: Iterator<Item = Result<RawBlock<'static>, Error>> + Send,
+    D: Decompressor,
+    NewParser: Fn() -> BlockParser<D> + Send + Sync,
+{
+    blobs.par_bridge().map_init(new_parser, |block_parser, blob| {
+        blob.and_then(|raw_block| block_parser.parse_block(raw_block).map(ParsedBlock::from))
+    })
+}
+
+/// Maps the primitive blocks of a blob iterator in parallel with `map`, then reduces the results with `reduce`.
+///
+/// Header and unknown blocks are skipped. `identity` must return a value which doesn't change the result when
+/// reduced with any other value, since it may be used any number of times.
+///
+/// Blocks are parsed with default block parsers. To use configured parsers, map and reduce the blocks of
+/// [`par_parse_blocks_with_parser`] instead.
+///
+/// # Errors
+///
+/// Will return `Err` if reading or parsing a blob, or `map` fails. Remaining blocks may not be processed then.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::parallel::par_map_reduce;
+/// use rosm_pbf_reader::BlobReader;
+///
+/// use std::fs::File;
+///
+/// let file = File::open("some.osm.pbf").unwrap();
+///
+/// let way_count = par_map_reduce(
+///     BlobReader::new(file),
+///     |block| Ok(block.primitivegroup.iter().map(|group| group.ways.len()).sum()),
+///     || 0,
+///     |a, b| a + b,
+/// )
+/// .unwrap();
+/// ```
+#[cfg(feature = "rayon")]
+pub fn par_map_reduce<Blobs, T, Map, Identity, Reduce>(
+    blobs: Blobs,
+    map: Map,
+    identity: Identity,
+    reduce: Reduce,
+) -> Result<T, Error>
+where
+    Blobs: Iterator<Item = Result<RawBlock<'static>, Error>> + Send,
+    T: Send,
+    Map: Fn(pbf::PrimitiveBlock) -> Result<T, Error> + Sync + Send,
+    Identity: Fn() -> T + Sync + Send,
+    Reduce: Fn(T, T) -> T + Sync + Send,
+{
+    par_parse_blocks(blobs)
+        .filter_map(|block| match block {
+            Ok(ParsedBlock::Primitive(primitive_block)) => Some(map(primitive_block)),
+            Ok(_) => None,
+            Err(error) => Some(Err(error)),
+        })
+        .try_reduce(identity, |a, b| Ok(reduce(a, b)))
+}
+
+#[cfg(test)]
+mod parallel_block_reader_tests {
+    use super::*;
+    use crate::test_utils::{way_blocks_pbf, write_blob};
+    use crate::BlobReader;
+
+    use prost::Message;
+
+    use std::io::Cursor;
+
+    fn way_id(block: ParsedBlock) -> i64 {
+        match block {
+            ParsedBlock::Primitive(primitive_block) => primitive_block.primitivegroup[0].ways[0].id,
+            _ => panic!("expected a primitive block"),
+        }
+    }
+
+    #[test]
+    fn ordered() {
+        let options = ParallelOptions {
+            thread_count: 4,
+            max_blocks_in_flight: 2,
+            ordered: true,
+            ..Default::default()
+        };
+
+        let blobs = BlobReader::new(Cursor::new(way_blocks_pbf(100)));
+        let ids: Vec<_> = ParallelBlockReader::new(blobs, options)
+            .map(|block| way_id(block.unwrap()))
+            .collect();
+
+        assert_eq!(ids, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unordered() {
+        let options = ParallelOptions {
+            thread_count: 3,
+            max_blocks_in_flight: 5,
+            ordered: false,
+            ..Default::default()
+        };
+
+        let blobs = BlobReader::new(Cursor::new(way_blocks_pbf(50)));
+        let mut ids: Vec<_> = ParallelBlockReader::new(blobs, options)
+            .map(|block| way_id(block.unwrap()))
+            .collect();
+
+        ids.sort();
+        assert_eq!(ids, (0..50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn errors_in_order() {
+        let mut pbf = way_blocks_pbf(3);
+        write_blob(&mut pbf, "OSMData", &[0xff]);
+        pbf.extend(way_blocks_pbf(1));
+        pbf.pop();
+
+        let blobs = BlobReader::new(Cursor::new(pbf));
+        let results: Vec<_> = ParallelBlockReader::new(blobs, ParallelOptions::default()).collect();
+
+        assert_eq!(results.len(), 5);
+        assert!(results[..3].iter().all(|result| result.is_ok()));
+        assert!(matches!(results[3], Err(Error::PbfParseError(_))));
+        assert!(matches!(results[4], Err(Error::IoError(_))));
+    }
+
+    #[test]
+    fn early_drop() {
+        let options = ParallelOptions {
+            max_blocks_in_flight: 1,
+            ..Default::default()
+        };
+
+        let blobs = BlobReader::new(Cursor::new(way_blocks_pbf(10)));
+        let mut blocks = ParallelBlockReader::new(blobs, options);
+
+        assert_eq!(way_id(blocks.next().unwrap().unwrap()), 0);
+    }
+
+    struct PanickingDecompressor;
+
+    impl Decompressor for PanickingDecompressor {
+        fn decompress(
+            &mut self,
+            _method: crate::CompressionMethod,
+            _input: &[u8],
+            _output: &mut [u8],
+        ) -> Result<usize, crate::DecompressionError> {
+            panic!("decompressor failure");
+        }
+    }
+
+    #[test]
+    fn parser_panic() {
+        let options = ParallelOptions {
+            thread_count: 2,
+            max_blocks_in_flight: 2,
+            ordered: true,
+            ..Default::default()
+        };
+
+        let compressed_blob = pbf::Blob {
+            raw_size: Some(16),
+            data: Some(pbf::blob::Data::ZlibData(vec![0; 8])),
+        };
+
+        let mut pbf = way_blocks_pbf(3);
+        write_blob(&mut pbf, "OSMData", &compressed_blob.encode_to_vec());
+        pbf.extend(way_blocks_pbf(3));
+
+        let blobs = BlobReader::new(Cursor::new(pbf));
+        let results: Vec<_> =
+            ParallelBlockReader::with_parser(blobs, options, || BlockParser::with_decompressor(PanickingDecompressor))
+                .collect();
+
+        assert_eq!(results.len(), 7);
+        assert!(matches!(&results[3], Err(Error::LogicError(message)) if message.contains("decompressor failure")));
+        assert!(results.iter().enumerate().all(|(i, result)| i == 3 || result.is_ok()));
+    }
+
+    #[test]
+    fn parser_factory_panic() {
+        let options = ParallelOptions {
+            thread_count: 2,
+            max_blocks_in_flight: 1,
+            ordered: true,
+            ..Default::default()
+        };
+
+        let blobs = BlobReader::new(Cursor::new(way_blocks_pbf(5)));
+        let results: Vec<_> = ParallelBlockReader::with_parser(blobs, options, || -> BlockParser {
+            panic!("factory failure");
+        })
+        .collect();
+
+        assert_eq!(results.len(), 5);
+        assert!(results
+            .iter()
+            .all(|result| matches!(result, Err(Error::LogicError(message)) if message.contains("factory failure"))));
+    }
+
+    #[test]
+    fn blob_iterator_panic() {
+        let blobs = BlobReader::new(Cursor::new(way_blocks_pbf(2))).chain(std::iter::from_fn(
+            || -> Option<Result<RawBlock<'static>, Error>> {
+                panic!("blob iterator failure");
+            },
+        ));
+        let results: Vec<_> = ParallelBlockReader::new(blobs, ParallelOptions::default()).collect();
+
+        assert_eq!(results.len(), 3);
+        assert!(results[..2].iter().all(Result::is_ok));
+        assert!(matches!(&results[2], Err(Error::LogicError(message)) if message.contains("blob iterator failure")));
+    }
+}
+
+#[cfg(all(test, feature = "rayon"))]
+mod rayon_tests {
+    use super::*;
+    use crate::test_utils::{way_blocks_pbf, write_blob};
+    use crate::BlobReader;
+
+    use prost::Message;
+
+    use std::io::Cursor;
+
+    fn test_pbf(block_count: i64) -> Vec<u8> {
+        let mut pbf = Vec::new();
+
+        let header_block = pbf::HeaderBlock::default();
+        let blob = pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(header_block.encode_to_vec())),
+        };
+
+        write_blob(&mut pbf, "OSMHeader", &blob.encode_to_vec());
+
+        pbf.extend(way_blocks_pbf(block_count));
+        pbf
+    }
+
+    #[test]
+    fn parse_blocks() {
+        let blobs = BlobReader::new(Cursor::new(test_pbf(20)));
+        let blocks: Vec<_> = par_parse_blocks(blobs).collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(blocks.len(), 21);
+        assert_eq!(
+            blocks
+                .iter()
+                .filter(|block| matches!(block, ParsedBlock::Header(_)))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn parse_blocks_strict() {
+        let header_block = pbf::HeaderBlock {
+            required_features: vec!["Foo".to_string()],
+            ..Default::default()
+        };
+        let blob = pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(header_block.encode_to_vec())),
+        };
+
+        let mut pbf = Vec::new();
+        write_blob(&mut pbf, "OSMHeader", &blob.encode_to_vec());
+
+        let new_parser = || {
+            let mut block_parser = BlockParser::default();
+            block_parser.set_strict(true);
+            block_parser
+        };
+
+        let blobs = BlobReader::new(Cursor::new(pbf.clone()));
+        assert!(par_parse_blocks(blobs).all(|block| block.is_ok()));
+
+        let blobs = BlobReader::new(Cursor::new(pbf));
+        let results: Vec<_> = par_parse_blocks_with_parser(blobs, new_parser).collect();
+        assert!(matches!(results[..], [Err(Error::UnsupportedFeature(_))]));
+    }
+
+    #[test]
+    fn map_reduce() {
+        let blobs = BlobReader::new(Cursor::new(test_pbf(20)));
+        let id_sum = par_map_reduce(
+            blobs,
+            |block| Ok(block.primitivegroup[0].ways[0].id),
+            || 0,
+            |a, b| a + b,
+        );
+
+        assert_eq!(id_sum.unwrap(), (0..20).sum());
+    }
+
+    #[test]
+    fn map_reduce_error() {
+        let mut pbf = test_pbf(5);
+        write_blob(&mut pbf, "OSMData", &[0xff]);
+
+        let blobs = BlobReader::new(Cursor::new(pbf));
+        let result = par_map_reduce(blobs, |_| Ok(1), || 0, |a, b| a + b);
+
+        assert!(matches!(result, Err(Error::PbfParseError(_))));
+    }
+}
@@ -0,0 +1,69 @@
+//! Helpers shared by unit tests.
+
+use crate::pbf;
+
+use prost::Message;
+
+/// Appends a blob with the given type and (already encoded) data to `pbf`.
+pub fn write_blob(pbf: &mut Vec<u8>, r#type: &str, data: &[u8]) {
+    let blob_header = pbf::BlobHeader {
+        r#type: r#type.to_string(),
+        indexdata: None,
+        datasize: data.len() as i32,
+    };
+
+    let blob_header = blob_header.encode_to_vec();
+    pbf.extend_from_slice(&(blob_header.len() as i32).to_be_bytes());
+    pbf.extend_from_slice(&blob_header);
+    pbf.extend_from_slice(data);
+}
+
+/// Returns a primitive block with a string table and a single tagged way.
+pub fn test_primitive_block() -> pbf::PrimitiveBlock {
+    let key_vals = ["", "highway", "residential"];
+
+    pbf::PrimitiveBlock {
+        stringtable: pbf::StringTable {
+            s: key_vals.iter().map(|s| s.as_bytes().to_vec()).collect(),
+        },
+        primitivegroup: vec![pbf::PrimitiveGroup {
+            ways: vec![pbf::Way {
+                id: 42,
+                keys: vec![1],
+                vals: vec![2],
+                refs: vec![1, 1, 1],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+/// Returns an OSM PBF containing `block_count` raw primitive blocks, each with a single way identified by the index of
+/// the block.
+pub fn way_blocks_pbf(block_count: i64) -> Vec<u8> {
+    let mut pbf = Vec::new();
+
+    for id in 0..block_count {
+        let primitive_block = pbf::PrimitiveBlock {
+            primitivegroup: vec![pbf::PrimitiveGroup {
+                ways: vec![pbf::Way {
+                    id,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let blob = pbf::Blob {
+            raw_size: None,
+            data: Some(pbf::blob::Data::Raw(primitive_block.encode_to_vec())),
+        };
+
+        write_blob(&mut pbf, "OSMData", &blob.encode_to_vec());
+    }
+
+    pbf
+}
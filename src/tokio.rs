@@ -0,0 +1,357 @@
+//! Asynchronous blob reading from Tokio's [`AsyncRead`] inputs.
+//!
+//! Blobs are validated the same way as in the synchronous [`read_blob`](crate::read_blob) and
+//! [`BlobReader`](crate::BlobReader), and the returned [`RawBlock`]s can be parsed with a
+//! [`BlockParser`](crate::BlockParser) as usual.
+
+use crate::{parse_blob_header, parse_header_size, BlobPosition, Error, ParsedBlobHeader, RawBlock, ReaderLimits};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+/// Reads the next blob from `pbf` asynchronously.
+///
+/// See [`crate::read_blob`] for the synchronous version.
+pub async fn read_blob<Input>(pbf: &mut Input) -> Option<Result<RawBlock<'static>, Error>>
+where
+    Input: AsyncRead + Unpin,
+{
+    read_blob_with_limits(pbf, &ReaderLimits::default()).await
+}
+
+/// Reads the next blob from `pbf` asynchronously, like [`read_blob`], but with custom size limits.
+pub async fn read_blob_with_limits<Input>(
+    pbf: &mut Input,
+    limits: &ReaderLimits,
+) -> Option<Result<RawBlock<'static>, Error>>
+where
+    Input: AsyncRead + Unpin,
+{
+    let mut header_size_buffer = [0u8; 4];
+
+    if let Err(error) = pbf.read_exact(&mut header_size_buffer).await {
+        return match error.kind() {
+            ErrorKind::UnexpectedEof => None,
+            _ => Some(Err(Error::IoError(error))),
+        };
+    }
+
+    Some(read_blob_inner(pbf, header_size_buffer, limits).await)
+}
+
+async fn read_blob_inner<Input>(
+    pbf: &mut Input,
+    header_size_buffer: [u8; 4],
+    limits: &ReaderLimits,
+) -> Result<RawBlock<'static>, Error>
+where
+    Input: AsyncRead + Unpin,
+{
+    let header_size = parse_header_size(header_size_buffer, limits)?;
+
+    let mut header_buffer = vec![0u8; header_size];
+    if let Err(error) = pbf.read_exact(&mut header_buffer).await {
+        return Err(Error::IoError(error));
+    }
+
+    let parsed_header = parse_blob_header(&header_buffer, limits)?;
+
+    let mut blob = vec![0u8; parsed_header.data_size];
+    if let Err(error) = pbf.read_exact(&mut blob).await {
+        return Err(Error::IoError(error));
+    }
+
+    Ok(parsed_header.into_raw_block(blob))
+}
+
+enum State {
+    HeaderSize {
+        buffer: [u8; 4],
+        filled: usize,
+    },
+    Header {
+        buffer: Vec<u8>,
+        filled: usize,
+    },
+    Data {
+        parsed_header: ParsedBlobHeader,
+        buffer: Vec<u8>,
+        filled: usize,
+    },
+    Finished,
+}
+
+/// Stream of the blobs of an asynchronous input, tracking the position of each blob.
+///
+/// This is the asynchronous counterpart of [`BlobReader`](crate::BlobReader): it stops after the first error, since
+/// the input can't be resynchronized after a partial or invalid blob.
+pub struct BlobStream<Input> {
+    pbf: Input,
+    limits: ReaderLimits,
+    next_position: BlobPosition,
+    state: State,
+}
+
+impl<Input> BlobStream<Input>
+where
+    Input: AsyncRead + Unpin,
+{
+    /// Creates a new `BlobStream` reading from the start of `pbf`.
+    pub fn new(pbf: Input) -> Self {
+        Self::with_limits(pbf, ReaderLimits::default())
+    }
+
+    /// Creates a new `BlobStream` reading from the start of `pbf`, with custom size limits.
+    pub fn with_limits(pbf: Input, limits: ReaderLimits) -> Self {
+        Self {
+            pbf,
+            limits,
+            next_position: BlobPosition { index: 0, offset: 0 },
+            state: State::HeaderSize {
+                buffer: [0u8; 4],
+                filled: 0,
+            },
+        }
+    }
+
+    /// Returns the position of the next blob to be read.
+    pub fn next_position(&self) -> BlobPosition {
+        self.next_position
+    }
+
+    /// Consumes the `BlobStream`, returning the underlying input.
+    pub fn into_inner(self) -> Input {
+        self.pbf
+    }
+
+    fn finish(&mut self, error: Error) -> Poll<Option<Result<RawBlock<'static>, Error>>> {
+        self.state = State::Finished;
+        Poll::Ready(Some(Err(error)))
+    }
+}
+
+/// Reads into `buffer[*filled..]` until it's full. Returns `false` if the end of `pbf` is reached before that.
+fn poll_fill<Input>(
+    pbf: &mut Input,
+    cx: &mut Context<'_>,
+    buffer: &mut [u8],
+    filled: &mut usize,
+) -> Poll<std::io::Result<bool>>
+where
+    Input: AsyncRead + Unpin,
+{
+    while *filled < buffer.len() {
+        let mut read_buffer = ReadBuf::new(&mut buffer[*filled..]);
+        ready!(Pin::new(&mut *pbf).poll_read(cx, &mut read_buffer))?;
+
+        match read_buffer.filled().len() {
+            0 => return Poll::Ready(Ok(false)),
+            read_size => *filled += read_size,
+        }
+    }
+
+    Poll::Ready(Ok(true))
+}
+
+impl<Input> Stream for BlobStream<Input>
+where
+    Input: AsyncRead + Unpin,
+{
+    type Item = Result<RawBlock<'static>, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            match &mut this.state {
+                State::HeaderSize { buffer, filled } => match ready!(poll_fill(&mut this.pbf, cx, buffer, filled)) {
+                    Ok(true) => match parse_header_size(*buffer, &this.limits) {
+                        Ok(header_size) => {
+                            this.state = State::Header {
+                                buffer: vec![0u8; header_size],
+                                filled: 0,
+                            };
+                        }
+                        Err(error) => return this.finish(error),
+                    },
+                    Ok(false) if *filled == 0 => {
+                        this.state = State::Finished;
+                        return Poll::Ready(None);
+                    }
+                    Ok(false) => return this.finish(Error::IoError(ErrorKind::UnexpectedEof.into())),
+                    Err(error) => return this.finish(Error::IoError(error)),
+                },
+                State::Header { buffer, filled } => match ready!(poll_fill(&mut this.pbf, cx, buffer, filled)) {
+                    Ok(true) => match parse_blob_header(buffer, &this.limits) {
+                        Ok(parsed_header) => {
+                            this.state = State::Data {
+                                buffer: vec![0u8; parsed_header.data_size],
+                                parsed_header,
+                                filled: 0,
+                            };
+                        }
+                        Err(error) => return this.finish(error),
+                    },
+                    Ok(false) => return this.finish(Error::IoError(ErrorKind::UnexpectedEof.into())),
+                    Err(error) => return this.finish(Error::IoError(error)),
+                },
+                State::Data { buffer, filled, .. } => match ready!(poll_fill(&mut this.pbf, cx, buffer, filled)) {
+                    Ok(true) => {
+                        let state = std::mem::replace(
+                            &mut this.state,
+                            State::HeaderSize {
+                                buffer: [0u8; 4],
+                                filled: 0,
+                            },
+                        );
+
+                        let State::Data {
+                            parsed_header, buffer, ..
+                        } = state
+                        else {
+                            unreachable!()
+                        };
+
+                        let blob_size = 4 + parsed_header.header_size as u64 + parsed_header.data_size as u64;
+
+                        let mut raw_block = parsed_header.into_raw_block(buffer);
+                        raw_block.position = Some(this.next_position);
+
+                        this.next_position.index += 1;
+                        this.next_position.offset += blob_size;
+
+                        return Poll::Ready(Some(Ok(raw_block)));
+                    }
+                    Ok(false) => return this.finish(Error::IoError(ErrorKind::UnexpectedEof.into())),
+                    Err(error) => return this.finish(Error::IoError(error)),
+                },
+                State::Finished => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod blob_stream_tests {
+    use super::*;
+    use crate::test_utils::write_blob;
+
+    use std::future::poll_fn;
+
+    /// Input which returns at most one byte per read, and is pending before every read.
+    struct TrickleReader<'a> {
+        data: &'a [u8],
+        pending: bool,
+    }
+
+    impl AsyncRead for TrickleReader<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            self.pending = !self.pending;
+
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            if let Some((first, remaining)) = self.data.split_first() {
+                buf.put_slice(&[*first]);
+                self.data = remaining;
+            }
+
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    async fn next<Input>(stream: &mut BlobStream<Input>) -> Option<Result<RawBlock<'static>, Error>>
+    where
+        Input: AsyncRead + Unpin,
+    {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    fn test_pbf() -> (Vec<u8>, u64) {
+        let mut pbf = Vec::new();
+        write_blob(&mut pbf, "OSMHeader", &[1, 2, 3]);
+        let second_offset = pbf.len() as u64;
+        write_blob(&mut pbf, "OSMData", &[4, 5]);
+        (pbf, second_offset)
+    }
+
+    #[tokio::test]
+    async fn read_blobs() {
+        let (pbf, _) = test_pbf();
+        let mut pbf = pbf.as_slice();
+
+        let first = read_blob(&mut pbf).await.unwrap().unwrap();
+        assert_eq!(first.r#type(), "OSMHeader");
+        assert_eq!(first.data(), [1, 2, 3]);
+
+        let second = read_blob(&mut pbf).await.unwrap().unwrap();
+        assert_eq!(second.r#type(), "OSMData");
+        assert_eq!(second.data(), [4, 5]);
+
+        assert!(read_blob(&mut pbf).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_partial_reads() {
+        let (pbf, second_offset) = test_pbf();
+        let mut stream = BlobStream::new(TrickleReader {
+            data: &pbf,
+            pending: false,
+        });
+
+        let first = next(&mut stream).await.unwrap().unwrap();
+        assert_eq!(first.position(), Some(BlobPosition { index: 0, offset: 0 }));
+        assert_eq!(first.data(), [1, 2, 3]);
+
+        let second = next(&mut stream).await.unwrap().unwrap();
+        assert_eq!(
+            second.position(),
+            Some(BlobPosition {
+                index: 1,
+                offset: second_offset
+            })
+        );
+        assert_eq!(second.data(), [4, 5]);
+
+        assert!(next(&mut stream).await.is_none());
+        assert_eq!(stream.next_position().offset, pbf.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn stream_stops_after_error() {
+        let (mut pbf, _) = test_pbf();
+        pbf.pop();
+
+        let mut stream = BlobStream::new(pbf.as_slice());
+        assert!(next(&mut stream).await.unwrap().is_ok());
+        assert!(matches!(next(&mut stream).await, Some(Err(Error::IoError(_)))));
+        assert!(next(&mut stream).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_limits() {
+        let (pbf, _) = test_pbf();
+
+        let limits = ReaderLimits {
+            blob_size_limit: 3,
+            ..Default::default()
+        };
+
+        let mut stream = BlobStream::with_limits(pbf.as_slice(), limits);
+        assert!(matches!(
+            next(&mut stream).await,
+            Some(Err(Error::BlobDataTooLarge { size: 3, limit: 3 }))
+        ));
+        assert!(next(&mut stream).await.is_none());
+    }
+}
@@ -1,6 +1,8 @@
 //! Various utilities, like timestamp and coordinate normalization.
 
-use crate::pbf;
+use crate::{pbf, Error};
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Normalizes `lat` and `lon` to nanodegrees and returns them in a `(latitude, longitude)` pair.
 pub fn normalize_coord(lat: i64, lon: i64, block: &pbf::PrimitiveBlock) -> (i64, i64) {
@@ -10,7 +12,125 @@ pub fn normalize_coord(lat: i64, lon: i64, block: &pbf::PrimitiveBlock) -> (i64,
     )
 }
 
-/// Normalizes a timestamp coming from [`pbf::Info`] or [`pbf::DenseInfo`] to nanoseconds.
+/// Normalizes `lat` and `lon` to nanodegrees like [`normalize_coord`], but returns an error instead of overflowing,
+/// or producing a coordinate outside the valid ±90° latitude and ±180° longitude ranges.
+pub fn checked_normalize_coord(lat: i64, lon: i64, block: &pbf::PrimitiveBlock) -> Result<LatLon, Error> {
+    let normalize = |value: i64, offset: i64| {
+        value
+            .checked_mul(block.granularity() as i64)
+            .and_then(|value| value.checked_add(offset))
+    };
+
+    match (normalize(lat, block.lat_offset()), normalize(lon, block.lon_offset())) {
+        (Some(lat), Some(lon)) => LatLon::new(lat, lon),
+        _ => Err(Error::LogicError(format!(
+            "normalizing coordinate {lat}, {lon} overflows"
+        ))),
+    }
+}
+
+/// Converts nanodegrees to degrees.
+pub(crate) fn nanodegrees_to_degrees(nanodegrees: i64) -> f64 {
+    nanodegrees as f64 / 1e9
+}
+
+/// A validated coordinate, stored in nanodegrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LatLon {
+    lat: i64,
+    lon: i64,
+}
+
+impl LatLon {
+    pub(crate) const MAX_LAT: i64 = 90_000_000_000;
+    pub(crate) const MAX_LON: i64 = 180_000_000_000;
+
+    /// Constructs a new `LatLon` from a latitude and longitude in nanodegrees.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the latitude is outside of ±90°, or the longitude is outside of ±180°.
+    pub fn new(lat: i64, lon: i64) -> Result<Self, Error> {
+        if (-Self::MAX_LAT..=Self::MAX_LAT).contains(&lat) && (-Self::MAX_LON..=Self::MAX_LON).contains(&lon) {
+            Ok(LatLon { lat, lon })
+        } else {
+            Err(Error::LogicError(format!(
+                "coordinate {lat}, {lon} (in nanodegrees) is out of range"
+            )))
+        }
+    }
+
+    /// Returns the latitude in nanodegrees.
+    pub fn lat(&self) -> i64 {
+        self.lat
+    }
+
+    /// Returns the longitude in nanodegrees.
+    pub fn lon(&self) -> i64 {
+        self.lon
+    }
+
+    /// Returns the latitude in degrees.
+    pub fn lat_degrees(&self) -> f64 {
+        nanodegrees_to_degrees(self.lat)
+    }
+
+    /// Returns the longitude in degrees.
+    pub fn lon_degrees(&self) -> f64 {
+        nanodegrees_to_degrees(self.lon)
+    }
+}
+
+/// Normalizes a timestamp coming from [`pbf::Info`] or [`pbf::DenseInfo`] to milliseconds since the Unix epoch.
 pub fn normalize_timestamp(timestamp: i64, block: &pbf::PrimitiveBlock) -> i64 {
     timestamp * block.date_granularity() as i64
 }
+
+/// Converts milliseconds since the Unix epoch to a `SystemTime`. Returns `None` if it's out of range.
+pub(crate) fn system_time_from_millis(millis: i64) -> Option<SystemTime> {
+    let duration = Duration::from_millis(millis.unsigned_abs());
+
+    if millis >= 0 {
+        UNIX_EPOCH.checked_add(duration)
+    } else {
+        UNIX_EPOCH.checked_sub(duration)
+    }
+}
+
+/// Returns the strings of a new string table. Index 0 is reserved as a delimiter of dense node tags, so it holds an
+/// empty string which is never referenced otherwise.
+pub(crate) fn new_string_table() -> Vec<Vec<u8>> {
+    vec![Vec::new()]
+}
+
+#[cfg(test)]
+mod util_tests {
+    use super::*;
+
+    #[test]
+    fn checked_coord() {
+        let block = pbf::PrimitiveBlock {
+            granularity: Some(100),
+            lat_offset: Some(1000),
+            lon_offset: Some(-1000),
+            ..Default::default()
+        };
+
+        let coord = checked_normalize_coord(475_000_000, -1_225_000_000, &block).unwrap();
+        assert_eq!((coord.lat(), coord.lon()), (47_500_001_000, -122_500_001_000));
+        assert_eq!((coord.lat_degrees(), coord.lon_degrees()), (47.500001, -122.500001));
+
+        assert!(checked_normalize_coord(900_000_000, 0, &block).is_err());
+        assert!(checked_normalize_coord(0, -1_800_000_000, &block).is_err());
+        assert!(checked_normalize_coord(i64::MAX / 10, 0, &block).is_err());
+
+        let block = pbf::PrimitiveBlock {
+            lat_offset: Some(i64::MAX),
+            ..Default::default()
+        };
+        assert!(checked_normalize_coord(1, 0, &block).is_err());
+
+        assert!(LatLon::new(90_000_000_000, -180_000_000_000).is_ok());
+        assert!(LatLon::new(-90_000_000_001, 0).is_err());
+    }
+}
@@ -0,0 +1,533 @@
+//! Utilities for writing OSM data in PBF format.
+
+#[cfg(feature = "flate2")]
+use flate2::write::ZlibEncoder;
+
+use crate::{pbf, CompressionMethod, Error, ReaderLimits};
+
+use prost::Message;
+
+use std::io::Write;
+
+/// Possible errors returned by [`Compressor`] implementations.
+#[derive(Debug)]
+pub enum CompressionError {
+    /// The given compression method isn't supported by the compressor.
+    UnsupportedCompression,
+    /// The given compression level isn't valid for the compression method.
+    InvalidLevel(i32),
+    /// An internal error occured during compression.
+    InternalError(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Trait for custom compression support, the counterpart of [`Decompressor`](crate::Decompressor).
+///
+/// A compressor is owned by its [`PbfWriter`], so implementations can keep state between blobs.
+pub trait Compressor {
+    /// Compresses `input` with `method`, returning the compressed data. `level` is the method specific compression
+    /// level, or `None` to use the method's default level.
+    fn compress(
+        &mut self,
+        method: CompressionMethod,
+        level: Option<i32>,
+        input: &[u8],
+    ) -> Result<Vec<u8>, CompressionError>;
+}
+
+/// The default blob compressor.
+///
+/// Supports ZLib compression (with levels from 0 to 9) if the `flate2` feature is enabled (it is by default).
+/// Zstandard, LZ4 and LZMA compression can be enabled with the `zstd`, `lz4` and `lzma` features respectively. Levels
+/// of Zstandard are passed to `zstd`, while levels of LZ4 and LZMA are ignored.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultCompressor;
+
+impl Compressor for DefaultCompressor {
+    #[allow(unused_variables)] // Unused if no compression features are enabled
+    fn compress(
+        &mut self,
+        method: CompressionMethod,
+        level: Option<i32>,
+        input: &[u8],
+    ) -> Result<Vec<u8>, CompressionError> {
+        match method {
+            #[cfg(feature = "flate2")]
+            CompressionMethod::Zlib => {
+                let level = match level {
+                    Some(level @ 0..=9) => flate2::Compression::new(level as u32),
+                    Some(level) => return Err(CompressionError::InvalidLevel(level)),
+                    None => flate2::Compression::default(),
+                };
+
+                let mut encoder = ZlibEncoder::new(Vec::new(), level);
+                match encoder.write_all(input).and_then(|()| encoder.finish()) {
+                    Ok(output) => Ok(output),
+                    Err(error) => Err(CompressionError::InternalError(Box::new(error))),
+                }
+            }
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => match zstd::bulk::compress(input, level.unwrap_or(0)) {
+                Ok(output) => Ok(output),
+                Err(error) => Err(CompressionError::InternalError(Box::new(error))),
+            },
+            #[cfg(feature = "lz4")]
+            CompressionMethod::Lz4 => Ok(lz4_flex::block::compress(input)),
+            #[cfg(feature = "lzma")]
+            CompressionMethod::Lzma => {
+                let mut output = Vec::new();
+                match lzma_rs::lzma_compress(&mut &*input, &mut output) {
+                    Ok(()) => Ok(output),
+                    Err(error) => Err(CompressionError::InternalError(Box::new(error))),
+                }
+            }
+            #[allow(unreachable_patterns)] // Unreachable if all compression features are enabled
+            _ => Err(CompressionError::UnsupportedCompression),
+        }
+    }
+}
+
+/// Compression of the blobs written by a [`PbfWriter`], or encoded by [`encode_blob`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Blob data is stored uncompressed.
+    #[default]
+    Raw,
+    /// Blob data is compressed with `method`, at the method specific `level`, or its default level if `None`.
+    Compressed {
+        method: CompressionMethod,
+        level: Option<i32>,
+    },
+}
+
+/// Wraps an encoded block into a blob, compressing it with `compressor` if needed, and setting its raw size.
+///
+/// # Examples
+///
+/// ```
+/// use rosm_pbf_reader::writer::{encode_blob, Compression, DefaultCompressor};
+/// use rosm_pbf_reader::CompressionMethod;
+///
+/// # #[cfg(feature = "flate2")]
+/// # {
+/// let compression = Compression::Compressed {
+///     method: CompressionMethod::Zlib,
+///     level: Some(9),
+/// };
+///
+/// let blob = encode_blob(&[0; 1024], compression, &mut DefaultCompressor).unwrap();
+/// assert_eq!(blob.raw_size, Some(1024));
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if compression fails, or `block` is too large to be stored in a blob.
+pub fn encode_blob<C>(block: &[u8], compression: Compression, compressor: &mut C) -> Result<pbf::Blob, Error>
+where
+    C: Compressor + ?Sized,
+{
+    let raw_size: i32 = block.len().try_into().map_err(|_err| Error::InvalidBlobData)?;
+
+    let data = match compression {
+        Compression::Raw => pbf::blob::Data::Raw(block.to_vec()),
+        Compression::Compressed { method, level } => {
+            let compressed_data = compressor
+                .compress(method, level, block)
+                .map_err(Error::CompressionError)?;
+
+            match method {
+                CompressionMethod::Lz4 => pbf::blob::Data::Lz4Data(compressed_data),
+                CompressionMethod::Lzma => pbf::blob::Data::LzmaData(compressed_data),
+                CompressionMethod::Zlib => pbf::blob::Data::ZlibData(compressed_data),
+                CompressionMethod::Zstd => pbf::blob::Data::ZstdData(compressed_data),
+            }
+        }
+    };
+
+    Ok(pbf::Blob {
+        raw_size: Some(raw_size),
+        data: Some(data),
+    })
+}
+
+/// Writer of header and primitive blocks, framing them into blobs like [`read_blob`](crate::read_blob) expects.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosm_pbf_reader::pbf;
+/// use rosm_pbf_reader::writer::PbfWriter;
+///
+/// use std::fs::File;
+/// use std::io::BufWriter;
+///
+/// let file = File::create("some.osm.pbf").unwrap();
+/// let mut writer = PbfWriter::new(BufWriter::new(file));
+///
+/// let header_block = pbf::HeaderBlock {
+///     required_features: vec!["OsmSchema-V0.6".to_string(), "DenseNodes".to_string()],
+///     ..Default::default()
+/// };
+///
+/// writer.write_header_block(&header_block).unwrap();
+/// writer.write_primitive_block(&pbf::PrimitiveBlock::default()).unwrap();
+/// writer.flush().unwrap();
+/// ```
+pub struct PbfWriter<Output, C: Compressor = DefaultCompressor> {
+    output: Output,
+    compressor: C,
+    compression: Compression,
+    limits: ReaderLimits,
+}
+
+impl<Output> PbfWriter<Output>
+where
+    Output: Write,
+{
+    /// Creates a new `PbfWriter` writing uncompressed blobs to `output`, with the default size limits of the OSM PBF
+    /// format.
+    pub fn new(output: Output) -> Self {
+        Self::with_limits(output, ReaderLimits::default())
+    }
+
+    /// Creates a new `PbfWriter` writing uncompressed blobs to `output`, with custom size limits.
+    pub fn with_limits(output: Output, limits: ReaderLimits) -> Self {
+        let mut writer = Self::with_compressor(output, DefaultCompressor);
+        writer.set_limits(limits);
+        writer
+    }
+}
+
+impl<Output, C> PbfWriter<Output, C>
+where
+    Output: Write,
+    C: Compressor,
+{
+    /// Creates a new `PbfWriter` writing uncompressed blobs to `output`, which uses `compressor` once compression is
+    /// enabled with [`PbfWriter::set_compression`].
+    pub fn with_compressor(output: Output, compressor: C) -> Self {
+        Self {
+            output,
+            compressor,
+            compression: Compression::Raw,
+            limits: ReaderLimits::default(),
+        }
+    }
+
+    /// Returns the size limits of this writer.
+    pub fn limits(&self) -> &ReaderLimits {
+        &self.limits
+    }
+
+    /// Sets the size limits of this writer.
+    pub fn set_limits(&mut self, limits: ReaderLimits) {
+        self.limits = limits;
+    }
+
+    /// Returns the compression of the blobs written by this writer.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Sets the compression of the blobs written by this writer.
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Returns a reference to the compressor of this writer.
+    pub fn compressor(&self) -> &C {
+        &self.compressor
+    }
+
+    /// Returns a mutable reference to the compressor of this writer.
+    pub fn compressor_mut(&mut self) -> &mut C {
+        &mut self.compressor
+    }
+
+    /// Writes `header_block` as an `OSMHeader` blob.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the encoded block exceeds a size limit, or writing to the output fails.
+    pub fn write_header_block(&mut self, header_block: &pbf::HeaderBlock) -> Result<(), Error> {
+        self.write_block("OSMHeader", &header_block.encode_to_vec())
+    }
+
+    /// Writes `primitive_block` as an `OSMData` blob.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the encoded block exceeds a size limit, or writing to the output fails.
+    pub fn write_primitive_block(&mut self, primitive_block: &pbf::PrimitiveBlock) -> Result<(), Error> {
+        self.write_block("OSMData", &primitive_block.encode_to_vec())
+    }
+
+    /// Writes an encoded block of type `r#type`, like `OSMHeader` or `OSMData`, as a blob compressed according to
+    /// [`PbfWriter::compression`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the block exceeds a size limit, compression fails, or writing to the output fails.
+    pub fn write_block(&mut self, r#type: &str, block: &[u8]) -> Result<(), Error> {
+        if block.len() >= self.limits.uncompressed_size_limit {
+            return Err(Error::UncompressedBlobTooLarge {
+                size: block.len(),
+                limit: self.limits.uncompressed_size_limit,
+            });
+        }
+
+        let blob = encode_blob(block, self.compression, &mut self.compressor)?;
+
+        self.write_blob(r#type, &blob)
+    }
+
+    /// Writes `blob` with a blob header of type `r#type`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the encoded blob or its header exceeds a size limit or `i32::MAX` bytes, or writing to the
+    /// output fails.
+    pub fn write_blob(&mut self, r#type: &str, blob: &pbf::Blob) -> Result<(), Error> {
+        let blob = blob.encode_to_vec();
+
+        if blob.len() >= self.limits.blob_size_limit {
+            return Err(Error::BlobDataTooLarge {
+                size: blob.len(),
+                limit: self.limits.blob_size_limit,
+            });
+        }
+
+        // Sizes are stored as `i32`, regardless of the limits
+        let datasize = i32::try_from(blob.len()).map_err(|_err| Error::BlobDataTooLarge {
+            size: blob.len(),
+            limit: i32::MAX as usize,
+        })?;
+
+        let blob_header = pbf::BlobHeader {
+            r#type: r#type.to_string(),
+            indexdata: None,
+            datasize,
+        }
+        .encode_to_vec();
+
+        if blob_header.len() >= self.limits.header_size_limit {
+            return Err(Error::BlobHeaderTooLarge {
+                size: blob_header.len(),
+                limit: self.limits.header_size_limit,
+            });
+        }
+
+        let header_size = i32::try_from(blob_header.len()).map_err(|_err| Error::BlobHeaderTooLarge {
+            size: blob_header.len(),
+            limit: i32::MAX as usize,
+        })?;
+
+        let mut write = || -> std::io::Result<()> {
+            self.output.write_all(&header_size.to_be_bytes())?;
+            self.output.write_all(&blob_header)?;
+            self.output.write_all(&blob)
+        };
+
+        write().map_err(Error::IoError)
+    }
+
+    /// Flushes the output.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if flushing the output fails.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.output.flush().map_err(Error::IoError)
+    }
+
+    /// Consumes the `PbfWriter`, returning the underlying output.
+    pub fn into_inner(self) -> Output {
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod compressor_tests {
+    use super::*;
+    use crate::{Decompressor, DefaultDecompressor};
+
+    fn decompress(blob: &pbf::Blob) -> Vec<u8> {
+        let (method, data) = match blob.data.as_ref().unwrap() {
+            pbf::blob::Data::Raw(data) => return data.clone(),
+            pbf::blob::Data::ZlibData(data) => (CompressionMethod::Zlib, data),
+            pbf::blob::Data::ZstdData(data) => (CompressionMethod::Zstd, data),
+            pbf::blob::Data::Lz4Data(data) => (CompressionMethod::Lz4, data),
+            pbf::blob::Data::LzmaData(data) => (CompressionMethod::Lzma, data),
+            _ => panic!("unexpected blob data"),
+        };
+
+        let mut output = vec![0; blob.raw_size.unwrap() as usize + 1];
+        let size = DefaultDecompressor.decompress(method, data, &mut output).unwrap();
+        output.truncate(size);
+        output
+    }
+
+    fn test_data() -> Vec<u8> {
+        (0..4096).map(|i| (i % 7) as u8).collect()
+    }
+
+    #[test]
+    fn raw() {
+        let blob = encode_blob(&test_data(), Compression::Raw, &mut DefaultCompressor).unwrap();
+        assert_eq!(blob.raw_size, Some(4096));
+        assert!(matches!(blob.data, Some(pbf::blob::Data::Raw(_))));
+        assert_eq!(decompress(&blob), test_data());
+    }
+
+    #[allow(unused_mut)] // Not mutated if no compression features are enabled
+    #[test]
+    fn supported_methods() {
+        let mut methods: Vec<(CompressionMethod, Option<i32>)> = Vec::new();
+        #[cfg(feature = "flate2")]
+        methods.extend([(CompressionMethod::Zlib, None), (CompressionMethod::Zlib, Some(9))]);
+        #[cfg(feature = "zstd")]
+        methods.extend([(CompressionMethod::Zstd, None), (CompressionMethod::Zstd, Some(19))]);
+        #[cfg(feature = "lz4")]
+        methods.push((CompressionMethod::Lz4, None));
+        #[cfg(feature = "lzma")]
+        methods.push((CompressionMethod::Lzma, None));
+
+        for (method, level) in methods {
+            let blob = encode_blob(
+                &test_data(),
+                Compression::Compressed { method, level },
+                &mut DefaultCompressor,
+            )
+            .unwrap();
+            assert_eq!(blob.raw_size, Some(4096));
+            assert_eq!(decompress(&blob), test_data());
+        }
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn invalid_zlib_level() {
+        let compression = Compression::Compressed {
+            method: CompressionMethod::Zlib,
+            level: Some(10),
+        };
+
+        assert!(matches!(
+            encode_blob(&test_data(), compression, &mut DefaultCompressor),
+            Err(Error::CompressionError(CompressionError::InvalidLevel(10)))
+        ));
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn unsupported_zstd() {
+        let compression = Compression::Compressed {
+            method: CompressionMethod::Zstd,
+            level: None,
+        };
+
+        assert!(matches!(
+            encode_blob(&test_data(), compression, &mut DefaultCompressor),
+            Err(Error::CompressionError(CompressionError::UnsupportedCompression))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod pbf_writer_tests {
+    use super::*;
+    use crate::test_utils::test_primitive_block;
+    use crate::{read_blob, Block, BlockParser};
+
+    #[test]
+    fn round_trip() {
+        let header_block = pbf::HeaderBlock {
+            required_features: vec!["OsmSchema-V0.6".to_string()],
+            writingprogram: Some("rosm_pbf_reader".to_string()),
+            ..Default::default()
+        };
+        let primitive_block = test_primitive_block();
+
+        let mut writer = PbfWriter::new(Vec::new());
+        writer.write_header_block(&header_block).unwrap();
+        writer.write_primitive_block(&primitive_block).unwrap();
+        writer.flush().unwrap();
+        let pbf = writer.into_inner();
+
+        let mut pbf = pbf.as_slice();
+        let mut parser = BlockParser::default();
+
+        let raw_block = read_blob(&mut pbf).unwrap().unwrap();
+        assert_eq!(raw_block.r#type(), "OSMHeader");
+        match parser.parse_block(raw_block) {
+            Ok(Block::Header(parsed_block)) => assert_eq!(parsed_block, header_block),
+            _ => panic!("expected a header block"),
+        }
+
+        let raw_block = read_blob(&mut pbf).unwrap().unwrap();
+        assert_eq!(raw_block.r#type(), "OSMData");
+        match parser.parse_block(raw_block) {
+            Ok(Block::Primitive(parsed_block)) => assert_eq!(parsed_block, primitive_block),
+            _ => panic!("expected a primitive block"),
+        }
+
+        assert!(read_blob(&mut pbf).is_none());
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn zlib_round_trip() {
+        let primitive_block = test_primitive_block();
+
+        let mut writer = PbfWriter::new(Vec::new());
+        writer.set_compression(Compression::Compressed {
+            method: crate::CompressionMethod::Zlib,
+            level: None,
+        });
+        writer.write_primitive_block(&primitive_block).unwrap();
+        let pbf = writer.into_inner();
+
+        let raw_block = read_blob(&mut pbf.as_slice()).unwrap().unwrap();
+        match BlockParser::default().parse_block(raw_block) {
+            Ok(Block::Primitive(parsed_block)) => assert_eq!(parsed_block, primitive_block),
+            _ => panic!("expected a primitive block"),
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let encoded_block = test_primitive_block().encode_to_vec();
+
+        let limits = ReaderLimits {
+            uncompressed_size_limit: encoded_block.len(),
+            ..Default::default()
+        };
+        let mut writer = PbfWriter::with_limits(Vec::new(), limits);
+        assert!(matches!(
+            writer.write_primitive_block(&test_primitive_block()),
+            Err(Error::UncompressedBlobTooLarge { .. })
+        ));
+
+        let limits = ReaderLimits {
+            blob_size_limit: encoded_block.len(),
+            ..Default::default()
+        };
+        let mut writer = PbfWriter::with_limits(Vec::new(), limits);
+        assert!(matches!(
+            writer.write_primitive_block(&test_primitive_block()),
+            Err(Error::BlobDataTooLarge { .. })
+        ));
+
+        let limits = ReaderLimits {
+            header_size_limit: 4,
+            ..Default::default()
+        };
+        let mut writer = PbfWriter::with_limits(Vec::new(), limits);
+        assert!(matches!(
+            writer.write_primitive_block(&test_primitive_block()),
+            Err(Error::BlobHeaderTooLarge { .. })
+        ));
+
+        assert!(writer.into_inner().is_empty());
+    }
+}